@@ -0,0 +1,65 @@
+use std::{env, fs, path::PathBuf};
+
+use directories::ProjectDirs;
+
+use crate::{error::{Error, Result}, teto::TetoType};
+
+//Everything the game reads goes through here so it works no matter which directory it's launched from
+//Piece shapes and the font are built into the binary, sounds and themes are read from the res directory
+//and what the game writes (settings, high score, exported fumens) lives in the user's config and data directories
+
+pub const FONT: &[u8] = include_bytes!("../res/PixelOperatorMono8-Bold.ttf");
+
+macro_rules! teto_shapes {
+    ($($dir:literal),*) => {
+        //The shape file of a teto in a rotation system's directory
+        pub fn get_teto_shape(dir: &str, teto_type: TetoType) -> Option<&'static str> {
+            match dir {
+                $($dir => match teto_type {
+                    TetoType::I => Some(include_str!(concat!("../res/", $dir, "/I"))),
+                    TetoType::O => Some(include_str!(concat!("../res/", $dir, "/O"))),
+                    TetoType::T => Some(include_str!(concat!("../res/", $dir, "/T"))),
+                    TetoType::S => Some(include_str!(concat!("../res/", $dir, "/S"))),
+                    TetoType::Z => Some(include_str!(concat!("../res/", $dir, "/Z"))),
+                    TetoType::J => Some(include_str!(concat!("../res/", $dir, "/J"))),
+                    TetoType::L => Some(include_str!(concat!("../res/", $dir, "/L"))),
+                    TetoType::Garbage => None
+                },)*
+                _ => None
+            }
+        }
+    };
+}
+
+teto_shapes!("srs", "ars", "nes");
+
+fn get_project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "ikeidjd", "tetris")
+}
+
+//The res directory next to the executable or in a directory above it, which finds the source tree's when running with cargo
+//Without one the game still runs, with no sounds and only the default theme
+pub fn get_res_dir() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    let dir = exe.ancestors().skip(1).map(|dir| dir.join("res")).find(|dir| dir.is_dir());
+    if dir.is_none() { log::warn!("There is no res directory next to {} or above it, sounds and themes are left out", exe.display()); }
+    dir
+}
+
+pub fn get_config_path(name: &str) -> Option<PathBuf> {
+    get_project_dirs().map(|dirs| dirs.config_dir().join(name))
+}
+
+pub fn get_data_path(name: &str) -> Option<PathBuf> {
+    get_project_dirs().map(|dirs| dirs.data_dir().join(name))
+}
+
+pub fn read_data(name: &str) -> Option<String> {
+    fs::read_to_string(get_data_path(name)?).ok()
+}
+
+pub fn write_data(name: &str, contents: &str) -> Result<()> {
+    let path = get_data_path(name).ok_or(Error::NoDirectory("data"))?;
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?; }
+    fs::write(&path, contents).map_err(|err| Error::io(path, err))
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use ggez::{audio::{SoundSource, Source}, Context};
+
+use crate::{events::{GameEvent, Subscriber}, settings::Settings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Move,
+    Rotate,
+    SoftDrop,
+    HardDrop,
+    Lock,
+    Hold,
+    Clear1,
+    Clear2,
+    Clear3,
+    Clear4,
+    TSpin,
+    Combo,
+    GameOver
+}
+
+impl Sfx {
+    const ALL: [Sfx; 13] = [Sfx::Move, Sfx::Rotate, Sfx::SoftDrop, Sfx::HardDrop, Sfx::Lock, Sfx::Hold, Sfx::Clear1, Sfx::Clear2, Sfx::Clear3, Sfx::Clear4, Sfx::TSpin, Sfx::Combo, Sfx::GameOver];
+
+    pub fn clear(count: i32) -> Self {
+        match count {
+            1 => Sfx::Clear1,
+            2 => Sfx::Clear2,
+            3 => Sfx::Clear3,
+            _ => Sfx::Clear4
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            Sfx::Move => "/sfx/move.wav",
+            Sfx::Rotate => "/sfx/rotate.wav",
+            Sfx::SoftDrop => "/sfx/soft_drop.wav",
+            Sfx::HardDrop => "/sfx/hard_drop.wav",
+            Sfx::Lock => "/sfx/lock.wav",
+            Sfx::Hold => "/sfx/hold.wav",
+            Sfx::Clear1 => "/sfx/clear1.wav",
+            Sfx::Clear2 => "/sfx/clear2.wav",
+            Sfx::Clear3 => "/sfx/clear3.wav",
+            Sfx::Clear4 => "/sfx/clear4.wav",
+            Sfx::TSpin => "/sfx/tspin.wav",
+            Sfx::Combo => "/sfx/combo.wav",
+            Sfx::GameOver => "/sfx/game_over.wav"
+        }
+    }
+}
+
+//Sounds that fail to load are left out, the game just stays quiet for them
+pub struct Audio {
+    sfx: HashMap<Sfx, Source>,
+    music: Option<Source>,
+    sfx_volume: f32,
+    music_volume: f32,
+    muted: bool,
+    //Sounds heard about from events, played with their pitch on the next update
+    pending: Vec<(Sfx, f32)>,
+    stop_music_pending: bool
+}
+
+impl Audio {
+    pub fn new(ctx: &Context, settings: &Settings) -> Self {
+        let mut music = Source::new(ctx, "/sfx/music.wav").ok();
+        if let Some(music) = music.as_mut() { music.set_repeat(true); }
+
+        let mut audio = Self {
+            sfx: Sfx::ALL.iter().filter_map(|sfx| Source::new(ctx, sfx.path()).ok().map(|source| (*sfx, source))).collect(),
+            music,
+            sfx_volume: settings.sfx_volume,
+            music_volume: settings.music_volume,
+            muted: settings.muted,
+            pending: Vec::new(),
+            stop_music_pending: false
+        };
+        audio.set_muted(settings.muted);
+
+        audio
+    }
+
+    pub fn update(&mut self, ctx: &Context) {
+        if self.stop_music_pending { self.stop_music(ctx); }
+        self.stop_music_pending = false;
+
+        for (sfx, pitch) in std::mem::take(&mut self.pending) { self.play_pitched(ctx, sfx, pitch); }
+    }
+
+    pub fn play(&mut self, ctx: &Context, sfx: Sfx) {
+        self.play_pitched(ctx, sfx, 1.0);
+    }
+
+    //Each combo step plays the combo sound a semitone higher, up to an octave
+    fn get_combo_pitch(combo_count: i32) -> f32 {
+        2.0f32.powf((combo_count - 1).min(12) as f32 / 12.0)
+    }
+
+    fn play_pitched(&mut self, ctx: &Context, sfx: Sfx, pitch: f32) {
+        if self.muted { return; }
+
+        if let Some(source) = self.sfx.get_mut(&sfx) {
+            source.set_volume(self.sfx_volume);
+            source.set_pitch(pitch);
+            let _ = source.play_detached(ctx);
+        }
+    }
+
+    pub fn play_music(&mut self, ctx: &Context) {
+        if let Some(music) = self.music.as_mut() { let _ = music.play(ctx); }
+    }
+
+    pub fn stop_music(&mut self, ctx: &Context) {
+        if let Some(music) = self.music.as_mut() { let _ = music.stop(ctx); }
+    }
+
+    pub fn set_volumes(&mut self, sfx_volume: f32, music_volume: f32) {
+        self.sfx_volume = sfx_volume;
+        self.music_volume = music_volume;
+        self.set_muted(self.muted);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        if let Some(music) = self.music.as_mut() { music.set_volume(if muted { 0.0 } else { self.music_volume }); }
+    }
+}
+
+impl Subscriber for Audio {
+    fn handle(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Moved => self.pending.push((Sfx::Move, 1.0)),
+            GameEvent::Rotated => self.pending.push((Sfx::Rotate, 1.0)),
+            GameEvent::SoftDropped => self.pending.push((Sfx::SoftDrop, 1.0)),
+            GameEvent::HardDropped { .. } => self.pending.push((Sfx::HardDrop, 1.0)),
+            GameEvent::Hold { .. } => self.pending.push((Sfx::Hold, 1.0)),
+            GameEvent::PieceLocked { tspin, .. } => {
+                self.pending.push((Sfx::Lock, 1.0));
+                if *tspin { self.pending.push((Sfx::TSpin, 1.0)); }
+            },
+            GameEvent::LinesCleared { count, combo, .. } => {
+                self.pending.push((Sfx::clear(*count), 1.0));
+                if *combo > 1 { self.pending.push((Sfx::Combo, Self::get_combo_pitch(*combo))); }
+            },
+            GameEvent::LevelUp { .. } => self.pending.push((Sfx::Combo, 2.0)),
+            GameEvent::TopOut => {
+                self.stop_music_pending = true;
+                self.pending.push((Sfx::GameOver, 1.0));
+            },
+            GameEvent::Completed => {
+                self.stop_music_pending = true;
+                self.pending.push((Sfx::Clear4, 1.0));
+            },
+            GameEvent::PieceSpawned { .. } | GameEvent::Scored { .. } => {}
+        }
+    }
+}
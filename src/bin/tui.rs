@@ -0,0 +1,255 @@
+use std::{collections::{HashMap, HashSet}, io::{self, Stdout, Write}, process, time::{Duration, Instant}};
+
+use crossterm::{cursor, event::{self, Event, KeyCode, KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags}, execute, queue, style::{self, Color, Stylize}, terminal};
+
+use tetris::{board::GRID_SIZE, error::Result, events::{GameEvent, Subscriber}, fumen, game::{self, Game, TICKS_PER_SECOND}, input::Input, logger, settings::Settings, teto::{TetoRot, TetoType, REPEAT_ACTIONS}};
+
+const TICK_TIME: Duration = Duration::from_nanos(1_000_000_000 / TICKS_PER_SECOND as u64);
+//After a stall the game slows down for a moment instead of running a burst of ticks
+const MAX_TICKS_PER_FRAME: u32 = 5;
+//Terminals without key release events only repeat held keys, a press is a tap until the terminal starts repeating it
+//Another press this soon after the last one is taken as the first repeat, the terminal's repeat delay is usually 500ms
+const REPEAT_DELAY: Duration = Duration::from_millis(550);
+//A repeating key stops counting as held once its repeats stop coming this often
+const REPEAT_GAP: Duration = Duration::from_millis(100);
+
+fn teto_color(teto_type: TetoType) -> Color {
+    match teto_type {
+        TetoType::I => Color::Cyan,
+        TetoType::O => Color::Yellow,
+        TetoType::T => Color::Magenta,
+        TetoType::S => Color::Green,
+        TetoType::Z => Color::Red,
+        TetoType::J => Color::Blue,
+        TetoType::L => Color::DarkYellow,
+        TetoType::Garbage => Color::Grey
+    }
+}
+
+//Same names as the ggez KeyCode Debug output so both frontends share Bindings
+fn key_name(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Char(' ') => Some("Space".to_owned()),
+        KeyCode::Char(c) => Some(c.to_ascii_uppercase().to_string()),
+        KeyCode::Left => Some("Left".to_owned()),
+        KeyCode::Right => Some("Right".to_owned()),
+        KeyCode::Up => Some("Up".to_owned()),
+        KeyCode::Down => Some("Down".to_owned()),
+        _ => None
+    }
+}
+
+//Rings the terminal bell on top out, the terminal's only sound
+struct Bell;
+
+impl Subscriber for Bell {
+    fn handle(&mut self, event: &GameEvent) {
+        if let GameEvent::TopOut = event { print!("\x07"); }
+    }
+}
+
+struct TuiState {
+    game: Game,
+    input: Input,
+    //When each held key was last pressed or repeated
+    pressed_keys: HashMap<String, Instant>,
+    //Keys pressed since the last tick, they are pressed for that tick even if they were released already
+    tapped_keys: HashSet<String>,
+    //Without release events, when each key was last pressed or repeated, held or not
+    last_presses: HashMap<String, Instant>,
+    has_release_events: bool,
+    //The last thing that went wrong, shown under the board
+    error: Option<String>,
+    quit: bool
+}
+
+impl TuiState {
+    fn new(fumen: Option<String>) -> Result<Self> {
+        Ok(Self {
+            game: Game::new(Settings { fumen, ..Settings::load() })?,
+            input: Input::new(),
+            pressed_keys: HashMap::new(),
+            tapped_keys: HashSet::new(),
+            last_presses: HashMap::new(),
+            has_release_events: false,
+            error: None,
+            quit: false
+        })
+    }
+
+    fn handle_events(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else { continue; };
+
+            if key.kind != KeyEventKind::Release {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.quit = true,
+                    KeyCode::F(2) => self.error = self.game.save_fumen().err().map(|err| format!("Couldn't save fumen: {}", err)),
+                    KeyCode::Char('r') if self.game.is_game_over() => self.error = self.game.restart().err().map(|err| err.to_string()),
+                    KeyCode::Tab if self.game.is_game_over() => self.game.settings.rotation_system = self.game.settings.rotation_system.next(),
+                    KeyCode::Char('x') if self.game.is_game_over() => self.game.settings.scoring = self.game.settings.scoring.next(),
+                    KeyCode::Char('m') if self.game.is_game_over() => self.game.settings.mode = self.game.settings.mode.next(),
+                    _ => {}
+                }
+            }
+
+            let Some(name) = key_name(key.code) else { continue; };
+            if self.has_release_events {
+                match key.kind {
+                    KeyEventKind::Release => { self.pressed_keys.remove(&name); },
+                    KeyEventKind::Repeat => { self.pressed_keys.insert(name, Instant::now()); },
+                    KeyEventKind::Press => {
+                        self.tapped_keys.insert(name.clone());
+                        self.pressed_keys.insert(name, Instant::now());
+                    }
+                }
+            }
+            else { self.press_without_release(name); }
+        }
+
+        if !self.has_release_events {
+            self.pressed_keys.retain(|_, time| time.elapsed() < REPEAT_GAP);
+            self.last_presses.retain(|_, time| time.elapsed() < REPEAT_DELAY);
+        }
+
+        Ok(())
+    }
+
+    //Only keys for actions that repeat (moving and soft dropping) are ever held, so IRS and IHS can't act on a guess
+    fn press_without_release(&mut self, name: String) {
+        let is_repeat = self.last_presses.insert(name.clone(), Instant::now()).is_some();
+        let can_hold = self.game.settings.bindings.get_actions([name.as_str()]).iter().all(|action| REPEAT_ACTIONS.contains(action));
+        if !is_repeat { self.tapped_keys.insert(name); }
+        else if can_hold { self.pressed_keys.insert(name, Instant::now()); }
+    }
+
+    //One tick
+    fn update(&mut self) {
+        self.input.update(self.game.settings.bindings.get_actions(self.pressed_keys.keys().chain(self.tapped_keys.iter()).map(|key| key.as_str())));
+        self.tapped_keys.clear();
+        if let Err(err) = self.game.update(&self.input) { self.error = Some(err.to_string()); }
+
+        self.game.publish_events(&mut [&mut Bell]);
+    }
+
+    fn draw_cell(&self, out: &mut Stdout, x: u16, y: u16, text: &str, color: Color) -> io::Result<()> {
+        queue!(out, cursor::MoveTo(x, y), style::PrintStyledContent(text.with(color)))
+    }
+
+    fn draw_preview(&self, out: &mut Stdout, x: u16, y: u16, label: &str, preview: Option<(TetoRot, TetoType)>) -> io::Result<()> {
+        queue!(out, cursor::MoveTo(x, y), style::Print(label))?;
+        for i in 0..2 { queue!(out, cursor::MoveTo(x, y + 1 + i), style::Print(" ".repeat(8)))?; }
+
+        if let Some((cells, teto_type)) = preview {
+            for pos in cells { self.draw_cell(out, x + pos.1 as u16 * 2, y + 1 + pos.0 as u16, "[]", teto_color(teto_type))?; }
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, out: &mut Stdout) -> io::Result<()> {
+        let board = self.game.get_board();
+        let cleared_rows = board.get_cleared_rows();
+
+        for i in 0..GRID_SIZE.i {
+            queue!(out, cursor::MoveTo(0, i as u16), style::Print("<!"))?;
+            for (j, cell) in board.get_row(i).iter().enumerate() {
+                let x = 2 + j as u16 * 2;
+                if cleared_rows.contains(&i) { self.draw_cell(out, x, i as u16, "[]", Color::White)?; }
+                else if let Some(teto_type) = cell { self.draw_cell(out, x, i as u16, "[]", teto_color(*teto_type))?; }
+                else { self.draw_cell(out, x, i as u16, " .", Color::DarkGrey)?; }
+            }
+            queue!(out, style::Print("!>"))?;
+        }
+        queue!(out, cursor::MoveTo(0, GRID_SIZE.i as u16), style::Print(format!("<!{}!>", "=".repeat(GRID_SIZE.j as usize * 2))))?;
+
+        if let Some(teto) = self.game.get_teto() {
+            let color = teto_color(teto.get_type());
+            for pos in teto.get_ghost_rot(board) {
+                if pos.0 >= 0 { self.draw_cell(out, 2 + pos.1 as u16 * 2, pos.0 as u16, "::", color)?; }
+            }
+            for pos in teto.get_rot() {
+                if pos.0 >= 0 { self.draw_cell(out, 2 + pos.1 as u16 * 2, pos.0 as u16, "[]", color)?; }
+            }
+        }
+
+        let ui_x = 4 + GRID_SIZE.j as u16 * 2 + 2;
+        let next_teto = self.game.get_next_teto();
+        self.draw_preview(out, ui_x, 0, "NEXT", Some((next_teto.get_preview(), next_teto.get_type())))?;
+        self.draw_preview(out, ui_x, 4, "HOLD", self.game.get_held_teto().map(|teto| (teto.get_preview(), teto.get_type())))?;
+
+        queue!(out, cursor::MoveTo(ui_x, 7), style::Print(format!("TIME:  {:<8}", game::format_frames(self.game.get_frame()))))?;
+        queue!(out, cursor::MoveTo(ui_x, 8), style::Print(format!("SCORE: {:<8}", self.game.get_score())))?;
+        queue!(out, cursor::MoveTo(ui_x, 9), style::Print(format!("COMBO: {:<8}", board.get_combo())))?;
+        queue!(out, cursor::MoveTo(ui_x, 10), style::Print(format!("HIGH:  {:<8}", self.game.get_high_score())))?;
+        queue!(out, cursor::MoveTo(ui_x, 11), style::Print(format!("PC:    {:<8}", self.game.get_statistics().perfect_clears)))?;
+        queue!(out, cursor::MoveTo(ui_x, 12), style::Print(format!("GRADE: {:<8}", self.game.get_grade().unwrap_or("-"))))?;
+        queue!(out, cursor::MoveTo(ui_x, 13), style::Print(format!("LINES: {:<8}", self.game.get_statistics().lines)))?;
+        let level = if self.game.get_mode().get().has_levels() { self.game.get_level().to_string() } else { "-".to_owned() };
+        queue!(out, cursor::MoveTo(ui_x, 14), style::Print(format!("LEVEL: {:<8}", level)))?;
+
+        if self.game.is_game_over() {
+            let result = if self.game.is_complete() { format!("{} CLEAR! {}", self.game.get_mode().get().name(), game::format_frames(self.game.get_frame())).green() } else { "GAME OVER".to_owned().red() };
+            queue!(out, cursor::MoveTo(ui_x, 15), style::Print(result))?;
+            queue!(out, cursor::MoveTo(ui_x, 16), style::Print("R: restart"))?;
+            queue!(out, cursor::MoveTo(ui_x, 17), style::Print(format!("TAB: rotation ({})   ", self.game.settings.rotation_system.get().name())))?;
+            queue!(out, cursor::MoveTo(ui_x, 18), style::Print(format!("X: scoring ({})   ", self.game.settings.scoring.get().name())))?;
+            queue!(out, cursor::MoveTo(ui_x, 19), style::Print(format!("M: mode ({})   ", self.game.settings.mode.get().name())))?;
+        }
+        else { queue!(out, cursor::MoveTo(ui_x, 15), terminal::Clear(terminal::ClearType::FromCursorDown))?; }
+        queue!(out, cursor::MoveTo(ui_x, GRID_SIZE.i as u16), style::Print("Q: quit  F2: save fumen"))?;
+        queue!(out, cursor::MoveTo(0, GRID_SIZE.i as u16 + 1), terminal::Clear(terminal::ClearType::CurrentLine))?;
+        if let Some(error) = &self.error { queue!(out, style::Print(error.as_str().red()))?; }
+
+        out.flush()
+    }
+}
+
+fn run(out: &mut Stdout, state: &mut TuiState) -> io::Result<()> {
+    //Time that has passed but hasn't been ticked yet
+    let mut lag = Duration::ZERO;
+    let mut last_frame = Instant::now();
+
+    while !state.quit {
+        let frame_start = Instant::now();
+        lag = (lag + (frame_start - last_frame)).min(TICK_TIME * MAX_TICKS_PER_FRAME);
+        last_frame = frame_start;
+
+        state.handle_events()?;
+        while lag >= TICK_TIME {
+            lag -= TICK_TIME;
+            state.update();
+        }
+        state.draw(out)?;
+
+        if let Some(left) = TICK_TIME.checked_sub(frame_start.elapsed()) { std::thread::sleep(left); }
+    }
+
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    logger::init();
+    let mut state = match TuiState::new(fumen::from_args()) {
+        Ok(state) => state,
+        Err(err) => {
+            log::error!("Couldn't start: {}", err);
+            process::exit(1);
+        }
+    };
+    let mut out = io::stdout();
+
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+    state.has_release_events = terminal::supports_keyboard_enhancement().unwrap_or(false) && execute!(out, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)).is_ok();
+
+    let result = run(&mut out, &mut state);
+
+    if state.has_release_events { let _ = execute!(out, PopKeyboardEnhancementFlags); }
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    //Only now that the terminal is back to normal can errors be printed
+    if let Err(err) = state.game.update_high_score() { log::error!("Couldn't save high score: {}", err); }
+    result
+}
@@ -1,59 +1,103 @@
-use ggez::{glam::Vec2, graphics::{Canvas, Color}, Context};
+#[cfg(feature = "gui")]
+use ggez::graphics::{Canvas, Color};
+
+use crate::teto::{Teto, TetoType};
+#[cfg(feature = "gui")]
+use crate::{theme::Theme, util::{self, CELL_SIZE, UI_SIZE}};
+
+//What locking a teto did, scoring rules turn this into points
+pub struct LockResult {
+    pub lines: i32,
+    pub tspin: bool,
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+    pub combo: i32,
+    pub game_over: bool
+}
+
+pub struct Index {
+    pub i: i32,
+    pub j: i32
+}
 
-use crate::{teto::Teto, util::{self, CELL_SIZE, GRID_SIZE, SCREEN_SIZE}};
+pub const GRID_SIZE: Index = Index { i: 20, j: 10 };
 
+#[derive(Clone)]
 pub struct Board {
-    data: [[Option<Color>; GRID_SIZE.j as usize]; GRID_SIZE.i as usize],
+    data: [[Option<TetoType>; GRID_SIZE.j as usize]; GRID_SIZE.i as usize],
     is_cleared: [bool; GRID_SIZE.i as usize],
-    clear_start_time: f32,
-    clear_time: f32,
-    clear_count: i32,
-    clear_i: f32,
-    combo_count: i32
+    //Ticks of the game, like everything else that happens over time in it
+    clear_start_frame: u32,
+    clear_frames: u32,
+    combo_count: i32,
+    //Whether the last clear was a tetris or t-spin
+    difficult_clear: bool,
+    collapse_start_frame: u32,
+    collapse_frames: u32,
+    //How many rows each row fell in the last collapse, used to slide it into place
+    row_drops: [i32; GRID_SIZE.i as usize]
 }
 
 impl Board {
-    pub fn new() -> Self {
+    const COLLAPSE_FRAMES: u32 = 7;
+
+    pub fn new(clear_frames: u32, animate_collapse: bool) -> Self {
         Self {
             data: [[None; GRID_SIZE.j as usize]; GRID_SIZE.i as usize],
             is_cleared: [false; GRID_SIZE.i as usize],
-            clear_start_time: 0.0,
-            clear_time: 0.25,
-            clear_count: 0,
-            clear_i: -1.0,
-            combo_count: 0
+            clear_start_frame: 0,
+            clear_frames,
+            combo_count: 0,
+            difficult_clear: false,
+            collapse_start_frame: 0,
+            collapse_frames: if animate_collapse { Self::COLLAPSE_FRAMES } else { 0 },
+            row_drops: [0; GRID_SIZE.i as usize]
         }
     }
 
-    pub fn add(&mut self, ctx: &Context, teto: Teto, score: &mut i32, game_over: &mut bool) {
+    pub fn add(&mut self, frame: u32, teto: Teto, tspin: bool) -> LockResult {
+        let mut game_over = false;
         for pos in teto.get_rot() {
-            if pos.0 < 0 || pos.0 >= GRID_SIZE.i || pos.1 < 0 || pos.1 >= GRID_SIZE.j { *game_over = true; }
-            else { self.data[pos.0 as usize][pos.1 as usize] = Some(teto.color()); }
+            if !(0..GRID_SIZE.i).contains(&pos.0) || !(0..GRID_SIZE.j).contains(&pos.1) { game_over = true; }
+            else { self.data[pos.0 as usize][pos.1 as usize] = Some(teto.get_type()); }
         }
 
-        self.clear_count = 0;
+        let mut clear_count = 0;
         'outer: for row in self.data.iter().enumerate() {
             for cell in row.1.iter() {
-                if matches!(cell, None) { continue 'outer; }
+                if cell.is_none() { continue 'outer; }
             }
 
-            self.clear_count += 1;
+            clear_count += 1;
             self.is_cleared[row.0] = true;
-            self.clear_start_time = util::get_time(ctx);
-            if self.clear_i == -1.0 { self.clear_i = row.0 as f32; }
+            self.clear_start_frame = frame;
         }
 
-        if self.clear_count == 0 {
+        if clear_count == 0 {
             self.combo_count = 0;
-            return;
+            return LockResult { lines: 0, tspin, back_to_back: false, perfect_clear: false, combo: 0, game_over };
         }
 
+        let difficult_clear = clear_count == 4 || tspin;
+        let back_to_back = difficult_clear && self.difficult_clear;
+        self.difficult_clear = difficult_clear;
+        let perfect_clear = self.data.iter().enumerate().all(|row| self.is_cleared[row.0] || row.1.iter().all(|cell| cell.is_none()));
+
         self.combo_count += 1;
-        *score += self.get_score();
+        LockResult { lines: clear_count, tspin, back_to_back, perfect_clear, combo: self.combo_count, game_over }
     }
 
-    pub fn update(&mut self, ctx: &Context) {
-        if util::get_time(ctx) - self.clear_start_time <= self.clear_time { return; }
+    pub fn update(&mut self, frame: u32) {
+        if frame.saturating_sub(self.clear_start_frame) < self.clear_frames { return; }
+        if !self.is_clearing() { return; }
+
+        self.row_drops = [0; GRID_SIZE.i as usize];
+        for i in 0..(GRID_SIZE.i as usize) {
+            if self.is_cleared[i] { continue; }
+            let drop = self.is_cleared[i..].iter().filter(|cleared| **cleared).count();
+            self.row_drops[i + drop] = drop as i32;
+        }
+        self.collapse_start_frame = frame;
 
         for i in 0..(GRID_SIZE.i as usize) {
             if !self.is_cleared[i] { continue; }
@@ -61,8 +105,6 @@ impl Board {
             self.lower(i);
             self.is_cleared[i] = false;
         }
-
-        self.clear_i = -1.0;
     }
 
     fn lower(&mut self, until: usize) {
@@ -73,57 +115,70 @@ impl Board {
         }
     }
 
-    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) {
-        let clear_time_elapsed = util::get_time(ctx) - self.clear_start_time;
-        let opacity = 1.0 - clear_time_elapsed / self.clear_time;
+    fn empty(&self, i: i32, j: i32) -> bool {
+        if i < 0 { return (0..GRID_SIZE.j).contains(&j); }
+        i < GRID_SIZE.i && (0..GRID_SIZE.j).contains(&j) && self.data[i as usize][j as usize].is_none()
+    }
 
-        for row in self.data.iter().enumerate() {
-            if self.is_cleared[row.0] {
-                for j in 0..GRID_SIZE.j { util::draw_cell_indices(canvas, row.0 as i32, j, Color::new(1.0, 1.0, 1.0, opacity)); }
-                continue;
-            }
+    pub fn filled(&self, i: i32, j: i32) -> bool {
+        !self.empty(i, j)
+    }
 
-            for cell in row.1.iter().enumerate() {
-                let color = match cell.1 {
-                    None => Color::BLACK,
-                    Some(color) => *color
-                };
-                util::draw_cell_indices(canvas, row.0 as i32, cell.0 as i32, color);
-            }
-        }
+    pub fn is_clearing(&self) -> bool {
+        self.is_cleared.iter().any(|cleared| *cleared)
+    }
 
-        if clear_time_elapsed <= self.clear_time {
-            let text = match self.clear_count {
-                1 => format!("single! {} score", self.get_score()),
-                2 => format!("double! {} score", self.get_score()),
-                3 => format!("triple! {} score", self.get_score()),
-                4 => format!("tetris! {} score", self.get_score()),
-                _ => "".to_owned()
-            };
-            util::draw_text_centered_on(canvas, &text, Vec2::new(SCREEN_SIZE.x / 2.0, (self.clear_i - 0.5) * CELL_SIZE), CELL_SIZE * 0.6, Color::new(opacity, opacity, opacity, 1.0));
-        }
+    pub fn set_clear_frames(&mut self, clear_frames: u32) {
+        self.clear_frames = clear_frames;
     }
 
-    fn get_score(&self) -> i32 {
-        (self.combo_count - 1) * 50 + match self.clear_count {
-            1 => 100,
-            2 => 300,
-            3 => 500,
-            4 => 800,
-            _ => 0
-        }
+    pub fn set_animate_collapse(&mut self, animate_collapse: bool) {
+        self.collapse_frames = if animate_collapse { Self::COLLAPSE_FRAMES } else { 0 };
     }
 
-    fn empty(&self, i: i32, j: i32) -> bool {
-        if i < 0 { return j >= 0 && j < GRID_SIZE.j; }
-        i < GRID_SIZE.i && j >= 0 && j < GRID_SIZE.j && matches!(self.data[i as usize][j as usize], None)
+    pub fn get_cleared_rows(&self) -> Vec<i32> {
+        (0..GRID_SIZE.i).filter(|i| self.is_cleared[*i as usize]).collect()
     }
 
-    pub fn filled(&self, i: i32, j: i32) -> bool {
-        !self.empty(i, j)
+    pub fn set_cell(&mut self, i: i32, j: i32, cell: Option<TetoType>) {
+        if !(0..GRID_SIZE.i).contains(&i) || !(0..GRID_SIZE.j).contains(&j) { return; }
+        self.data[i as usize][j as usize] = cell;
+    }
+
+    pub fn get_row(&self, i: i32) -> [Option<TetoType>; GRID_SIZE.j as usize] {
+        self.data[i as usize]
     }
 
     pub fn get_combo(&self) -> i32 {
         self.combo_count
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "gui")]
+impl Board {
+    //frame is the game's tick, which stands still while paused
+    pub fn draw(&self, frame: u32, canvas: &mut Canvas, theme: &Theme) {
+        let opacity = 1.0 - frame.saturating_sub(self.clear_start_frame) as f32 / self.clear_frames as f32;
+
+        if let Some(grid) = theme.grid { util::draw_rect(canvas, UI_SIZE.x - 1.0, 0.0, GRID_SIZE.j as f32 * CELL_SIZE + 1.0, GRID_SIZE.i as f32 * CELL_SIZE, grid); }
+
+        for row in self.data.iter().enumerate() {
+            if self.is_cleared[row.0] {
+                for j in 0..GRID_SIZE.j { util::draw_cell_indices(canvas, row.0 as i32, j, Color::new(1.0, 1.0, 1.0, opacity)); }
+                continue;
+            }
+
+            for j in 0..GRID_SIZE.j { util::draw_cell_indices(canvas, row.0 as i32, j, theme.empty); }
+        }
+
+        let collapse_left = if self.collapse_frames > 0 { (1.0 - frame.saturating_sub(self.collapse_start_frame) as f32 / self.collapse_frames as f32).max(0.0) } else { 0.0 };
+        for row in self.data.iter().enumerate() {
+            if self.is_cleared[row.0] { continue; }
+
+            let y = (row.0 as f32 - self.row_drops[row.0] as f32 * collapse_left) * CELL_SIZE;
+            for cell in row.1.iter().enumerate() {
+                if let Some(teto_type) = cell.1 { theme.draw_block(canvas, cell.0 as f32 * CELL_SIZE + UI_SIZE.x, y, theme.color(teto_type)); }
+            }
+        }
+    }
+}
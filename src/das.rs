@@ -0,0 +1,58 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::input::{Action, Input};
+
+//Delay and repeat are in ticks, an action fires when pressed, again once it's been held for delay ticks and then every repeat ticks
+pub struct Das {
+    delay: u32,
+    repeat: u32,
+    //Ticks each held action has been held for
+    actions_held_frames: HashMap<Action, u32>,
+    //Presses that haven't acted on a teto yet, kept through ARE and line clear delay so a tap there moves the next one
+    taps: HashSet<Action>
+}
+
+impl Das {
+    pub fn new(delay: u32, repeat: u32) -> Self {
+        Self {
+            delay,
+            repeat,
+            actions_held_frames: HashMap::new(),
+            taps: HashSet::new()
+        }
+    }
+
+    //Called once every tick, also while there is no teto to move so the next one starts out charged
+    //Only taps of the buffered actions wait for the next teto, the rest are dropped when there is none
+    pub fn update(&mut self, input: &Input, actions: &[Action], buffered: &[Action]) {
+        for action in actions {
+            if input.is_just_pressed(*action) {
+                self.actions_held_frames.insert(*action, 0);
+                self.taps.insert(*action);
+            }
+            else if input.is_pressed(*action) { *self.actions_held_frames.entry(*action).or_insert(0) += 1; }
+            else { self.actions_held_frames.remove(action); }
+        }
+        self.taps.retain(|action| buffered.contains(action) || input.is_just_pressed(*action));
+    }
+
+    //Called after a teto got to act on the taps
+    pub fn clear_taps(&mut self) {
+        self.taps.clear();
+    }
+
+    pub fn is_repeat(&self, action: Action) -> bool {
+        if self.taps.contains(&action) { return true; }
+        let Some(frames) = self.actions_held_frames.get(&action).copied() else { return false; };
+        frames >= self.delay && (frames - self.delay).is_multiple_of(self.repeat.max(1))
+    }
+
+    pub fn get_delay(&self) -> u32 {
+        self.delay
+    }
+
+    //Ticks an action has been held towards its first repeat, delay once it's repeating
+    pub fn get_charge(&self, action: Action) -> u32 {
+        self.actions_held_frames.get(&action).map_or(0, |frames| (*frames).min(self.delay))
+    }
+}
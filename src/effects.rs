@@ -0,0 +1,159 @@
+use ggez::{glam::Vec2, graphics::{Canvas, Color}};
+
+use crate::{board::GRID_SIZE, events::{GameEvent, Row, Subscriber}, settings::EffectToggles, teto::TetoType, theme::Theme, util::{self, CELL_SIZE, UI_SIZE}};
+
+//Colors are kept as the teto they came from (None is white) so they follow theme changes
+struct Particle {
+    pos: Vec2,
+    vel: Vec2,
+    teto_type: Option<TetoType>,
+    life: f32
+}
+
+//A fading rectangle over the board, used for lock flashes and hard drop trails
+struct Fade {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+    teto_type: Option<TetoType>,
+    alpha: f32,
+    life: f32,
+    duration: f32
+}
+
+pub struct Effects {
+    toggles: EffectToggles,
+    particles: Vec<Particle>,
+    fades: Vec<Fade>,
+    shake_time: f32,
+    shake_strength: f32
+}
+
+impl Effects {
+    const LOCK_FLASH_TIME: f32 = 0.15;
+    const LEVEL_FLASH_TIME: f32 = 0.5;
+    const TRAIL_TIME: f32 = 0.2;
+    const PARTICLE_TIME: f32 = 0.6;
+    const PARTICLES_PER_CELL: i32 = 3;
+    const GRAVITY: f32 = 900.0;
+    const SHAKE_TIME: f32 = 0.3;
+
+    pub fn new(toggles: EffectToggles) -> Self {
+        Self {
+            toggles,
+            particles: Vec::new(),
+            fades: Vec::new(),
+            shake_time: 0.0,
+            shake_strength: 0.0
+        }
+    }
+
+    pub fn set_toggles(&mut self, toggles: EffectToggles) {
+        self.toggles = toggles;
+        if !toggles.particles { self.particles.clear(); }
+        if !toggles.lock_flash || !toggles.hard_drop_trails || !toggles.level_flash { self.fades.clear(); }
+        if !toggles.screen_shake { self.shake_time = 0.0; }
+    }
+
+    pub fn lock(&mut self, cells: &[(i32, i32)]) {
+        if !self.toggles.lock_flash { return; }
+
+        for cell in cells {
+            self.fades.push(Fade { x: cell.1 as f32 * CELL_SIZE + UI_SIZE.x, y: cell.0 as f32 * CELL_SIZE, w: CELL_SIZE - 1.0, h: CELL_SIZE - 1.0, teto_type: None, alpha: 1.0, life: Self::LOCK_FLASH_TIME, duration: Self::LOCK_FLASH_TIME });
+        }
+    }
+
+    //The whole board flashes white
+    pub fn level_up(&mut self) {
+        if !self.toggles.level_flash { return; }
+
+        self.fades.push(Fade { x: UI_SIZE.x, y: 0.0, w: GRID_SIZE.j as f32 * CELL_SIZE, h: GRID_SIZE.i as f32 * CELL_SIZE, teto_type: None, alpha: 0.6, life: Self::LEVEL_FLASH_TIME, duration: Self::LEVEL_FLASH_TIME });
+    }
+
+    //cells are where the teto ended up, distance is how many rows it fell
+    pub fn hard_drop(&mut self, cells: &[(i32, i32)], distance: i32, teto_type: TetoType) {
+        if !self.toggles.hard_drop_trails || distance <= 0 { return; }
+
+        for cell in cells {
+            if cells.iter().any(|other| other.1 == cell.1 && other.0 < cell.0) { continue; }
+            self.fades.push(Fade { x: cell.1 as f32 * CELL_SIZE + UI_SIZE.x, y: (cell.0 - distance) as f32 * CELL_SIZE, w: CELL_SIZE - 1.0, h: distance as f32 * CELL_SIZE, teto_type: Some(teto_type), alpha: 0.35, life: Self::TRAIL_TIME, duration: Self::TRAIL_TIME });
+        }
+    }
+
+    pub fn clear_rows(&mut self, rows: &[(i32, Row)]) {
+        if !self.toggles.particles { return; }
+
+        for (i, row) in rows {
+            for (j, teto_type) in row.iter().enumerate() {
+                let center = Vec2::new((j as f32 + 0.5) * CELL_SIZE + UI_SIZE.x, (*i as f32 + 0.5) * CELL_SIZE);
+                for _ in 0..Self::PARTICLES_PER_CELL {
+                    let vel = Vec2::new(rand::random_range(-150.0..150.0), rand::random_range(-350.0..-50.0));
+                    self.particles.push(Particle { pos: center, vel, teto_type: *teto_type, life: Self::PARTICLE_TIME });
+                }
+            }
+        }
+    }
+
+    pub fn shake(&mut self, strength: f32) {
+        if !self.toggles.screen_shake { return; }
+
+        self.shake_time = Self::SHAKE_TIME;
+        self.shake_strength = strength;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in self.particles.iter_mut() {
+            particle.vel.y += Self::GRAVITY * dt;
+            particle.pos += particle.vel * dt;
+            particle.life -= dt;
+        }
+        self.particles.retain(|particle| particle.life > 0.0);
+
+        for fade in self.fades.iter_mut() { fade.life -= dt; }
+        self.fades.retain(|fade| fade.life > 0.0);
+
+        self.shake_time = (self.shake_time - dt).max(0.0);
+    }
+
+    pub fn get_shake_offset(&self) -> Vec2 {
+        if self.shake_time <= 0.0 { return Vec2::ZERO; }
+
+        let strength = self.shake_strength * self.shake_time / Self::SHAKE_TIME;
+        Vec2::new(rand::random_range(-strength..=strength), rand::random_range(-strength..=strength))
+    }
+
+    fn get_color(theme: &Theme, teto_type: Option<TetoType>) -> Color {
+        teto_type.map_or(Color::WHITE, |teto_type| theme.color(&teto_type))
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, theme: &Theme) {
+        for fade in self.fades.iter() {
+            let mut color = Self::get_color(theme, fade.teto_type);
+            color.a = fade.alpha * fade.life / fade.duration;
+            util::draw_rect(canvas, fade.x, fade.y, fade.w, fade.h, color);
+        }
+
+        for particle in self.particles.iter() {
+            let mut color = Self::get_color(theme, particle.teto_type);
+            color.a = particle.life / Self::PARTICLE_TIME;
+            let size = CELL_SIZE * 0.2;
+            util::draw_rect(canvas, particle.pos.x - size / 2.0, particle.pos.y - size / 2.0, size, size, color);
+        }
+    }
+}
+
+impl Subscriber for Effects {
+    fn handle(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::HardDropped { cells, distance, teto_type } => self.hard_drop(cells, *distance, *teto_type),
+            GameEvent::PieceLocked { cells, .. } => self.lock(cells),
+            GameEvent::LevelUp { .. } => self.level_up(),
+            GameEvent::LinesCleared { rows, count, perfect_clear, .. } => {
+                self.clear_rows(rows);
+                if *count >= 4 || *perfect_clear { self.shake(CELL_SIZE * 0.3); }
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::{fmt, io, path::PathBuf};
+
+//Everything that can go wrong outside of the game's rules, with the file it happened to when there is one
+#[derive(Debug)]
+pub enum Error {
+    Io { path: PathBuf, source: io::Error },
+    //A file that was read but doesn't make sense
+    InvalidAsset { path: String, reason: String },
+    Fumen(String),
+    //The system has no config or data directory to save to
+    NoDirectory(&'static str)
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io { path: path.into(), source }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::InvalidAsset { path, reason } => write!(f, "{} is invalid: {}", path, reason),
+            Error::Fumen(reason) => write!(f, "Invalid fumen: {}", reason),
+            Error::NoDirectory(kind) => write!(f, "There is no {} directory to save to", kind)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl From<Error> for ggez::GameError {
+    fn from(err: Error) -> Self {
+        ggez::GameError::CustomError(err.to_string())
+    }
+}
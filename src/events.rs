@@ -0,0 +1,71 @@
+use crate::{board::GRID_SIZE, teto::{TetoRot, TetoType}};
+
+pub type Row = [Option<TetoType>; GRID_SIZE.j as usize];
+
+//Things that happened in the game, each subscriber reacts to the ones it cares about
+#[derive(Debug)]
+pub enum GameEvent {
+    Moved,
+    Rotated,
+    SoftDropped,
+    HardDropped { cells: TetoRot, distance: i32, teto_type: TetoType },
+    PieceSpawned { teto_type: TetoType },
+    //teto_type is the teto that went into hold
+    Hold { teto_type: TetoType },
+    PieceLocked { cells: TetoRot, teto_type: TetoType, tspin: bool },
+    //rows are the cleared rows with what was in them before clearing, score is what the clear was worth
+    LinesCleared { rows: Vec<(i32, Row)>, count: i32, tspin: bool, b2b: bool, combo: i32, perfect_clear: bool, score: i32 },
+    Scored { points: i32 },
+    LevelUp { level: i32 },
+    TopOut,
+    //The mode's goal was reached
+    Completed
+}
+
+pub trait Subscriber {
+    fn handle(&mut self, event: &GameEvent);
+}
+
+//Holds the events of an update until they are published, so subscribers don't need to live inside the game
+pub struct EventBus {
+    events: Vec<GameEvent>
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new()
+        }
+    }
+
+    //Every event is logged here, the ones that happen many times a second only at trace level
+    pub fn emit(&mut self, event: GameEvent) {
+        match event {
+            GameEvent::Moved | GameEvent::Rotated | GameEvent::SoftDropped => log::trace!("{:?}", event),
+            _ => log::debug!("{:?}", event)
+        }
+        self.events.push(event);
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.events.len()
+    }
+
+    //Events emitted since the count was first
+    pub fn get_events_since(&self, first: usize) -> &[GameEvent] {
+        &self.events[first..]
+    }
+
+    //Every subscriber sees every event, in the order they were emitted
+    pub fn publish(&mut self, subscribers: &mut [&mut dyn Subscriber]) {
+        for event in self.events.drain(..) {
+            for subscriber in subscribers.iter_mut() { subscriber.handle(&event); }
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,243 @@
+use std::{env, fs};
+
+use crate::{board::{Board, GRID_SIZE}, teto::{self, Teto, TetoRot, TetoType}};
+
+//Fumen (v115) is the format setups are shared in, only the first page is read and written
+//Its field is 23 rows plus a garbage row, our board is the bottom 20 of those rows
+const FIELD_WIDTH: i32 = 10;
+const FIELD_TOP: i32 = 23;
+const FIELD_BLOCKS: i32 = (FIELD_TOP + 1) * FIELD_WIDTH;
+const HIDDEN_ROWS: i32 = FIELD_TOP - GRID_SIZE.i;
+
+const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+//Where the F2 hotkey saves the board, in the data directory next to the high score
+pub const EXPORT_FILE: &str = "fumen.txt";
+
+//Our rotation states are 0, R, 2, L while fumen numbers them 2, R, 0, L
+const ROTATIONS: [usize; 4] = [2, 1, 0, 3];
+
+pub struct FumenPiece {
+    pub teto_type: TetoType,
+    pub rot: usize,
+    pub cells: TetoRot
+}
+
+pub struct Fumen {
+    pub rows: Vec<[Option<TetoType>; GRID_SIZE.j as usize]>,
+    pub piece: Option<FumenPiece>
+}
+
+fn to_block(cell: Option<TetoType>) -> i32 {
+    match cell {
+        None => 0,
+        Some(TetoType::I) => 1,
+        Some(TetoType::L) => 2,
+        Some(TetoType::O) => 3,
+        Some(TetoType::Z) => 4,
+        Some(TetoType::T) => 5,
+        Some(TetoType::J) => 6,
+        Some(TetoType::S) => 7,
+        Some(TetoType::Garbage) => 8
+    }
+}
+
+fn from_block(block: i32) -> Option<TetoType> {
+    match block {
+        1 => Some(TetoType::I),
+        2 => Some(TetoType::L),
+        3 => Some(TetoType::O),
+        4 => Some(TetoType::Z),
+        5 => Some(TetoType::T),
+        6 => Some(TetoType::J),
+        7 => Some(TetoType::S),
+        8 => Some(TetoType::Garbage),
+        _ => None
+    }
+}
+
+//Cells of a piece around its fumen center as (i, j) offsets, i pointing down
+fn piece_cells(teto_type: TetoType, fumen_rot: usize) -> TetoRot {
+    let cells: [(i32, i32); 4] = match teto_type {
+        TetoType::I => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+        TetoType::T => [(0, 0), (-1, 0), (1, 0), (0, 1)],
+        TetoType::O => [(0, 0), (1, 0), (0, 1), (1, 1)],
+        TetoType::L => [(0, 0), (-1, 0), (1, 0), (1, 1)],
+        TetoType::J => [(0, 0), (-1, 0), (1, 0), (-1, 1)],
+        TetoType::S => [(0, 0), (-1, 0), (0, 1), (1, 1)],
+        TetoType::Z => [(0, 0), (1, 0), (0, 1), (-1, 1)],
+        TetoType::Garbage => return Vec::new()
+    };
+
+    //The table above is in fumen's (x, y) with y pointing up
+    cells.iter().map(|(x, y)| match fumen_rot {
+        0 => (*y, -*x),
+        1 => (*x, *y),
+        2 => (-*y, *x),
+        _ => (-*x, -*y)
+    }).collect()
+}
+
+//v115 keeps some pieces one cell off their center, this is how far the center is from the stored location as (i, j)
+//Fumen rotations are 0 reverse, 1 right, 2 spawn and 3 left
+fn location_offset(teto_type: TetoType, fumen_rot: usize) -> (i32, i32) {
+    match (teto_type, fumen_rot) {
+        (TetoType::O, 3) => (1, 1),
+        (TetoType::O, 0) | (TetoType::I, 0) | (TetoType::Z, 3) => (0, 1),
+        (TetoType::O, 2) | (TetoType::I, 3) | (TetoType::S, 2) | (TetoType::Z, 2) => (1, 0),
+        (TetoType::S, 1) => (0, -1),
+        _ => (0, 0)
+    }
+}
+
+fn push_value(data: &mut Vec<u8>, mut value: i32, digits: usize) {
+    for _ in 0..digits {
+        data.push(DIGITS[(value % 64) as usize]);
+        value /= 64;
+    }
+}
+
+fn pop_value(data: &mut impl Iterator<Item = i32>, digits: u32) -> Result<i32, String> {
+    let mut value = 0;
+    for digit in 0..digits { value += data.next().ok_or("Fumen ends too early")? * 64i32.pow(digit); }
+    Ok(value)
+}
+
+pub fn encode(board: &Board, teto: Option<&Teto>) -> String {
+    let mut blocks = vec![0; FIELD_BLOCKS as usize];
+    for i in 0..GRID_SIZE.i {
+        for (j, cell) in board.get_row(i).iter().enumerate() { blocks[((i + HIDDEN_ROWS) * FIELD_WIDTH) as usize + j] = to_block(*cell); }
+    }
+
+    //Run-length encoded differences from the previous page, which for the first page is an empty field
+    let mut data = Vec::new();
+    let mut start = 0;
+    while start < blocks.len() {
+        let count = blocks[start..].iter().take_while(|block| **block == blocks[start]).count();
+        push_value(&mut data, (blocks[start] + 8) * FIELD_BLOCKS + count as i32 - 1, 2);
+        start += count;
+    }
+    if blocks.iter().all(|block| *block == 0) { push_value(&mut data, 0, 1); }
+
+    let piece = teto.and_then(|teto| {
+        let cells = teto.get_rot();
+        //Try our own rotation state first since I, S, Z and O look the same in more than one
+        let rots = [ROTATIONS[teto.get_rot_state()]].into_iter().chain(ROTATIONS);
+        rots.filter_map(|rot| teto::alignment(&piece_cells(teto.get_type(), rot), &cells).map(|center| {
+            let offset = location_offset(teto.get_type(), rot);
+            (rot, (center.0 - offset.0, center.1 - offset.1))
+        })).next()
+            .filter(|(_, location)| (-HIDDEN_ROWS..GRID_SIZE.i).contains(&location.0) && (0..FIELD_WIDTH).contains(&location.1))
+            .map(|(rot, location)| (to_block(Some(teto.get_type())), rot, (location.0 + HIDDEN_ROWS) * FIELD_WIDTH + location.1))
+    });
+
+    //Piece, rotation and position, then the colorize flag, a clear lock flag means the piece locks like in the editor
+    let (block, rot, location) = piece.unwrap_or((0, 0, 0));
+    push_value(&mut data, block + rot as i32 * 8 + location * 32 + 30720, 3);
+
+    //Fumen breaks long strings up with a ? after 42 characters and then every 47
+    let mut fumen = "v115@".to_owned();
+    for (n, c) in data.iter().enumerate() {
+        if n >= 42 && (n - 42) % 47 == 0 { fumen.push('?'); }
+        fumen.push(*c as char);
+    }
+    fumen
+}
+
+pub fn decode(fumen: &str) -> Result<Fumen, String> {
+    let start = fumen.find("115@").ok_or("Not a v115 fumen")? + 4;
+    let mut data = fumen[start..].bytes().filter(|c| *c != b'?').take_while(|c| !c.is_ascii_whitespace()).map(|c| DIGITS.iter().position(|digit| *digit == c).map(|value| value as i32).ok_or(format!("Invalid fumen character '{}'", c as char))).collect::<Result<Vec<i32>, String>>()?.into_iter();
+
+    let mut blocks = Vec::new();
+    while (blocks.len() as i32) < FIELD_BLOCKS {
+        let value = pop_value(&mut data, 2)?;
+        let count = value % FIELD_BLOCKS + 1;
+        if value == 8 * FIELD_BLOCKS + FIELD_BLOCKS - 1 { pop_value(&mut data, 1)?; }
+        blocks.extend(std::iter::repeat_n(value / FIELD_BLOCKS - 8, count as usize));
+    }
+    if blocks.len() as i32 > FIELD_BLOCKS { return Err("Fumen field is too big".to_owned()); }
+
+    let rows = (HIDDEN_ROWS..FIELD_TOP).map(|row| {
+        let mut cells = [None; GRID_SIZE.j as usize];
+        for (j, cell) in cells.iter_mut().enumerate() { *cell = from_block(blocks[(row * FIELD_WIDTH) as usize + j]); }
+        cells
+    }).collect();
+
+    let action = pop_value(&mut data, 3)?;
+    let piece = from_block(action % 8).map(|teto_type| {
+        let fumen_rot = (action / 8 % 4) as usize;
+        let location = action / 32 % FIELD_BLOCKS;
+        let offset = location_offset(teto_type, fumen_rot);
+        let center = (location / FIELD_WIDTH - HIDDEN_ROWS + offset.0, location % FIELD_WIDTH + offset.1);
+        FumenPiece { teto_type, rot: ROTATIONS[fumen_rot], cells: piece_cells(teto_type, fumen_rot).iter().map(|cell| (cell.0 + center.0, cell.1 + center.1)).collect() }
+    });
+
+    Ok(Fumen { rows, piece })
+}
+
+//The setup passed with --fumen, either a fumen string or a file containing one
+pub fn from_args() -> Option<String> {
+    let arg = env::args().skip_while(|arg| arg != "--fumen").nth(1)?;
+    let fumen = fs::read_to_string(&arg).unwrap_or(arg);
+    match decode(&fumen) {
+        Ok(_) => Some(fumen.trim().to_owned()),
+        Err(err) => {
+            log::error!("Couldn't load fumen: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotation::RotationSystemType;
+
+    fn sorted(mut cells: TetoRot) -> TetoRot {
+        cells.sort();
+        cells
+    }
+
+    //An O in spawn orientation resting on the floor at columns 4 and 5 and a vertical I (left) against the left wall,
+    //written the way fumen.zui.jp and tetris-fumen store them, one row above their center
+    #[test]
+    fn decodes_offset_pieces() {
+        let fumen = decode("v115@vhATLJ").unwrap();
+        let piece = fumen.piece.unwrap();
+        assert_eq!(piece.teto_type, TetoType::O);
+        assert_eq!(sorted(piece.cells), vec![(18, 4), (18, 5), (19, 4), (19, 5)]);
+
+        let fumen = decode("v115@vhAZEJ").unwrap();
+        let piece = fumen.piece.unwrap();
+        assert_eq!(piece.teto_type, TetoType::I);
+        assert_eq!(sorted(piece.cells), vec![(16, 0), (17, 0), (18, 0), (19, 0)]);
+    }
+
+    #[test]
+    fn encodes_offset_pieces() {
+        let board = Board::new(0, false);
+        let mut teto = Teto::new(TetoType::O, RotationSystemType::Srs).unwrap();
+        assert!(teto.place(0, &[(18, 4), (18, 5), (19, 4), (19, 5)]));
+        assert_eq!(encode(&board, Some(&teto)), "v115@vhATLJ");
+    }
+
+    #[test]
+    fn round_trips_board_and_piece() {
+        let mut board = Board::new(0, false);
+        for j in 0..9 { board.set_cell(19, j, Some(TetoType::Garbage)); }
+        board.set_cell(18, 0, Some(TetoType::T));
+        board.set_cell(18, 1, Some(TetoType::S));
+
+        for teto_type in [TetoType::I, TetoType::O, TetoType::T, TetoType::S, TetoType::Z, TetoType::J, TetoType::L] {
+            for (fumen_rot, rot) in ROTATIONS.iter().enumerate() {
+                let cells: TetoRot = piece_cells(teto_type, fumen_rot).iter().map(|cell| (cell.0 + 10, cell.1 + 4)).collect();
+                let mut teto = Teto::new(teto_type, RotationSystemType::Srs).unwrap();
+                assert!(teto.place(*rot, &cells));
+
+                let fumen = decode(&encode(&board, Some(&teto))).unwrap();
+                assert_eq!(sorted(fumen.piece.unwrap().cells), sorted(cells));
+                for i in 0..GRID_SIZE.i { assert_eq!(fumen.rows[i as usize], board.get_row(i), "row {}", i); }
+            }
+        }
+    }
+}
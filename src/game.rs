@@ -0,0 +1,527 @@
+use std::fs;
+
+use crate::{assets, board::Board, das::Das, error::{Error, Result}, events::{EventBus, GameEvent, Subscriber}, fumen, input::{Action, Input}, mode::{ModeType, Timings}, practice::{Practice, Snapshot}, scoring::{Clear, ScoringType}, settings::Settings, teto::{Teto, TetoType, BUFFERED_ACTIONS, ONE_G, REPEAT_ACTIONS}};
+
+const HIGH_SCORE_FILE: &str = "high_score.txt";
+const LEGACY_HIGH_SCORE_PATH: &str = "./res/high_score.txt";
+//The first teto spawns on the first tick like every other one, so keys held when the game starts already act on it
+const FIRST_SPAWN_FRAME: u32 = 1;
+
+//The game runs at a fixed number of ticks a second whatever the frame rate, everything in it that takes time is counted in ticks
+pub const TICKS_PER_SECOND: u32 = 60;
+
+//Seconds from the settings in ticks
+pub fn to_frames(seconds: f32) -> u32 {
+    (seconds * TICKS_PER_SECOND as f32).round() as u32
+}
+
+//Ticks as minutes, seconds and hundredths, like 2:05.50
+pub fn format_frames(frames: u32) -> String {
+    let hundredths = frames * 100 / TICKS_PER_SECOND;
+    format!("{}:{:02}.{:02}", hundredths / 6000, hundredths / 100 % 60, hundredths % 100)
+}
+
+//Seconds a row from the settings in 1/ONE_G rows a tick
+pub fn to_gravity(seconds: f32) -> u32 {
+    (ONE_G as f32 / (seconds * TICKS_PER_SECOND as f32)).round() as u32
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statistics {
+    pub pieces: i32,
+    pub lines: i32,
+    pub perfect_clears: i32
+}
+
+impl Subscriber for Statistics {
+    fn handle(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::PieceLocked { .. } => self.pieces += 1,
+            GameEvent::LinesCleared { count, perfect_clear, .. } => {
+                self.lines += count;
+                if *perfect_clear { self.perfect_clears += 1; }
+            },
+            _ => {}
+        }
+    }
+}
+
+//The rules of the game without any window or terminal attached
+pub struct Game {
+    pub settings: Settings,
+    //The mode from the settings when the game started, changing the setting only applies on restart
+    mode: ModeType,
+    //Same for the scoring rules, so a finished game keeps its score and grade
+    scoring: ScoringType,
+    board: Board,
+    das: Das,
+
+    teto: Teto,
+    next_teto: Teto,
+    held_teto: Option<Teto>,
+    tetos: Vec<TetoType>,
+
+    can_hold: bool,
+    //Ticks since the game started
+    frame: u32,
+    //Set while waiting out ARE and line clear delay, the current teto spawns on this tick
+    spawn_frame: Option<u32>,
+    score: i32,
+    high_score: i32,
+    level: i32,
+    //The tick each section of levels was finished on, the last one ends when the game is complete
+    splits: Vec<u32>,
+    game_over: bool,
+    //Set when the game ended by reaching the mode's goal instead of topping out
+    complete: bool,
+    statistics: Statistics,
+    //Cells the current teto has been soft dropped
+    soft_dropped: i32,
+
+    //Some while in practice mode
+    practice: Option<Practice>,
+    //Set for practice games and once the editor, a retry or undo was used, these games don't count for the high score
+    assisted: bool,
+
+    events: EventBus
+}
+
+impl Game {
+    pub fn new(settings: Settings) -> Result<Self> {
+        Teto::check_shapes()?;
+
+        let mut tetos = Teto::get_teto_bag();
+        let mut game = Self {
+            mode: settings.mode,
+            scoring: settings.scoring,
+            board: Board::new(to_frames(settings.line_clear_delay), settings.effects.row_collapse),
+            das: Das::new(to_frames(settings.das), to_frames(settings.arr)),
+
+            teto: Teto::new(tetos.swap_remove(rand::random_range(0..tetos.len())), settings.rotation_system)?,
+            next_teto: Teto::new(tetos.swap_remove(rand::random_range(0..tetos.len())), settings.rotation_system)?,
+            held_teto: None,
+            tetos,
+
+            can_hold: true,
+            frame: 0,
+            spawn_frame: Some(FIRST_SPAWN_FRAME),
+            score: 0,
+            //Older versions kept the high score in the res directory of the directory they were launched from
+            high_score: assets::read_data(HIGH_SCORE_FILE).or_else(|| fs::read_to_string(LEGACY_HIGH_SCORE_PATH).ok()).and_then(|high_score| high_score.trim().parse().ok()).unwrap_or(0),
+            level: settings.mode.get().get_start_level(),
+            splits: Vec::new(),
+            game_over: false,
+            complete: false,
+            statistics: Statistics::default(),
+            soft_dropped: 0,
+
+            practice: None,
+            assisted: false,
+
+            events: EventBus::new(),
+
+            settings
+        };
+        game.load_setup()?;
+
+        Ok(game)
+    }
+
+    //Returns the old teto, not the new one
+    fn pop_teto(&mut self) -> Result<Teto> {
+        let queued = self.practice.as_mut().filter(|practice| !practice.queue.is_empty()).map(|practice| practice.queue.remove(0));
+        let teto_type = queued.unwrap_or_else(|| {
+            if self.tetos.is_empty() { self.tetos = Teto::get_teto_bag(); }
+            self.tetos.swap_remove(rand::random_range(0..self.tetos.len()))
+        });
+        let new_teto = Teto::new(teto_type, self.settings.rotation_system)?;
+        Ok(std::mem::replace(&mut self.teto, std::mem::replace(&mut self.next_teto, new_teto)))
+    }
+
+    //False when holding isn't allowed right now
+    fn hold(&mut self) -> Result<bool> {
+        if !self.can_hold { return Ok(false); }
+        self.emit(GameEvent::Hold { teto_type: self.teto.get_type() });
+
+        match self.held_teto.as_mut() {
+            None => self.held_teto = Some(self.pop_teto()?),
+            Some(teto) => {
+                std::mem::swap(teto, &mut self.teto);
+                self.teto.reset();
+                self.can_hold = false;
+            }
+        }
+        self.held_teto.as_mut().unwrap().reset();
+        self.emit(GameEvent::PieceSpawned { teto_type: self.teto.get_type() });
+        Ok(true)
+    }
+
+    //IHS and IRS: keys already held down when a new teto spawns act on it immediately, an IHS hold is the hold for the teto it brings out
+    fn spawn(&mut self, input: &Input) -> Result<()> {
+        self.spawn_frame = None;
+        //The first teto of a game doesn't raise the level
+        if self.statistics.pieces > 0 { self.set_level(self.mode.get().get_spawn_level(self.level)); }
+        self.emit(GameEvent::PieceSpawned { teto_type: self.teto.get_type() });
+        if input.is_pressed(Action::Hold) && self.hold()? { self.can_hold = false; }
+        self.teto.initial_rotate(input, &self.board);
+        self.update_teto_speed();
+        self.teto.spawn_fall(&self.board);
+        Ok(())
+    }
+
+    fn update_teto_speed(&mut self) {
+        self.teto.set_gravity(self.practice.as_ref().is_none_or(|practice| practice.gravity));
+        self.teto.set_fall_speed(self.mode.get().get_gravity(self.level).unwrap_or_else(|| to_gravity(self.settings.fall_duration)));
+        self.teto.set_lock_delay(self.get_timings().lock_delay);
+    }
+
+    pub fn update_high_score(&mut self) -> Result<()> {
+        if !self.assisted && self.score > self.high_score {
+            self.high_score = self.score;
+            assets::write_data(HIGH_SCORE_FILE, &self.score.to_string())?;
+        }
+        Ok(())
+    }
+
+    //The new game starts even when the high score of the old one couldn't be saved or the setup couldn't be loaded, the first error is returned after
+    pub fn restart(&mut self) -> Result<()> {
+        let saved = self.update_high_score();
+        self.mode = self.settings.mode;
+        self.scoring = self.settings.scoring;
+        log::info!("New {} game with {} rotation and {} scoring{}", self.mode.get().name(), self.settings.rotation_system.get().name(), self.scoring.get().name(), if self.practice.is_some() { " in practice" } else { "" });
+
+        self.board = Board::new(to_frames(self.settings.line_clear_delay), self.settings.effects.row_collapse);
+        self.das = Das::new(to_frames(self.settings.das), to_frames(self.settings.arr));
+
+        self.tetos = Teto::get_teto_bag();
+        self.teto = Teto::new(self.tetos.swap_remove(rand::random_range(0..self.tetos.len())), self.settings.rotation_system)?;
+        self.next_teto = Teto::new(self.tetos.swap_remove(rand::random_range(0..self.tetos.len())), self.settings.rotation_system)?;
+        self.held_teto = None;
+
+        self.can_hold = true;
+        self.frame = 0;
+        self.spawn_frame = Some(FIRST_SPAWN_FRAME);
+        self.score = 0;
+        self.level = self.mode.get().get_start_level();
+        self.splits = Vec::new();
+        self.game_over = false;
+        self.complete = false;
+        self.statistics = Statistics::default();
+        self.soft_dropped = 0;
+        self.assisted = self.practice.is_some();
+
+        let loaded = self.load_setup();
+        saved.and(loaded)
+    }
+
+    fn load_setup(&mut self) -> Result<()> {
+        match self.settings.fumen.clone() {
+            Some(fumen) => self.load_fumen(&fumen),
+            None => Ok(())
+        }
+    }
+
+    //Replaces the board with a fumen setup, its piece becomes the current teto if it has one
+    pub fn load_fumen(&mut self, fumen: &str) -> Result<()> {
+        let fumen = fumen::decode(fumen).map_err(Error::Fumen)?;
+
+        for (i, row) in fumen.rows.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() { self.board.set_cell(i as i32, j as i32, *cell); }
+        }
+
+        if let Some(piece) = fumen.piece {
+            let mut teto = Teto::new(piece.teto_type, self.settings.rotation_system)?;
+            if teto.place(piece.rot, &piece.cells) {
+                self.teto = teto;
+                self.spawn_frame = None;
+                self.emit(GameEvent::PieceSpawned { teto_type: self.teto.get_type() });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn to_fumen(&self) -> String {
+        fumen::encode(&self.board, self.get_teto())
+    }
+
+    pub fn save_fumen(&self) -> Result<()> {
+        assets::write_data(fumen::EXPORT_FILE, &self.to_fumen())
+    }
+
+    //One tick, frontends call this TICKS_PER_SECOND times a second with the input at that moment
+    pub fn update(&mut self, input: &Input) -> Result<()> {
+        if self.game_over || self.is_editing() { return Ok(()); }
+
+        self.frame += 1;
+        self.board.update(self.frame);
+        self.das.update(input, &REPEAT_ACTIONS, &BUFFERED_ACTIONS);
+
+        if let Some(spawn_frame) = self.spawn_frame {
+            if self.frame < spawn_frame { return Ok(()); }
+            self.spawn(input)?;
+        }
+
+        let held = input.is_just_pressed(Action::Hold) && self.hold()?;
+        self.update_teto_speed();
+        if held { self.teto.spawn_fall(&self.board); }
+
+        let timings = self.get_timings();
+        let first_event = self.events.get_count();
+        self.teto.update(input, &self.board, &self.das, &mut self.events);
+        self.das.clear_taps();
+        let soft_dropped = self.events.get_events_since(first_event).iter().filter(|event| matches!(event, GameEvent::SoftDropped)).count() as i32;
+        let hard_dropped = self.events.get_events_since(first_event).iter().map(|event| if let GameEvent::HardDropped { distance, .. } = event { *distance } else { 0 }).sum();
+        self.soft_dropped += soft_dropped;
+        self.add_score(self.scoring.get().get_drop_score(soft_dropped, hard_dropped));
+
+        if self.teto.is_dead() {
+            if self.practice.is_some() {
+                let snapshot = self.snapshot();
+                if let Some(practice) = self.practice.as_mut() { practice.push_undo(snapshot); }
+            }
+
+            let tspin = self.teto.is_tspin(&self.board);
+            let teto = self.pop_teto()?;
+            let (cells, teto_type) = (teto.get_rot(), teto.get_type());
+
+            self.board.set_clear_frames(timings.line_clear_delay);
+            let result = self.board.add(self.frame, teto, tspin);
+            let points = if result.lines > 0 || result.tspin { self.scoring.get().get_clear_score(&Clear { lines: result.lines, tspin: result.tspin, back_to_back: result.back_to_back, perfect_clear: result.perfect_clear, combo: result.combo, level: self.level, level_count: self.level - self.mode.get().get_start_level() + 1, soft_dropped: self.soft_dropped }) } else { 0 };
+            self.emit(GameEvent::PieceLocked { cells, teto_type, tspin });
+            self.add_score(points);
+
+            self.game_over = result.game_over;
+            self.can_hold = true;
+            self.soft_dropped = 0;
+
+            if self.game_over {
+                self.emit(GameEvent::TopOut);
+                return Ok(());
+            }
+            if self.board.is_clearing() {
+                let rows = self.board.get_cleared_rows().into_iter().map(|i| (i, self.board.get_row(i))).collect();
+                self.emit(GameEvent::LinesCleared { rows, count: result.lines, tspin: result.tspin, b2b: result.back_to_back, combo: result.combo, perfect_clear: result.perfect_clear, score: points });
+
+                self.set_level(self.mode.get().get_clear_level(self.level, result.lines, &self.statistics));
+                if self.mode.get().is_complete(self.level, &self.statistics) {
+                    if self.splits.last() != Some(&self.frame) { self.splits.push(self.frame); }
+                    self.game_over = true;
+                    self.complete = true;
+                    self.emit(GameEvent::Completed);
+                    return Ok(());
+                }
+            }
+
+            let delay = timings.are + if self.board.is_clearing() { timings.line_clear_delay } else { 0 };
+            if delay > 0 { self.spawn_frame = Some(self.frame + delay); }
+            else { self.spawn(input)?; }
+        }
+        Ok(())
+    }
+
+    //The mode's delays at the current level, or the ones from the settings without lock delay
+    //Free play has no lock delay, pieces lock as soon as they can't fall
+    fn get_timings(&self) -> Timings {
+        self.mode.get().get_timings(self.level).unwrap_or(Timings { are: to_frames(self.settings.are), line_clear_delay: to_frames(self.settings.line_clear_delay), lock_delay: 0 })
+    }
+
+    //Finishing a section keeps its time and flashes
+    fn set_level(&mut self, level: i32) {
+        let section_size = self.mode.get().get_section_size();
+        let sections = level / section_size - self.level / section_size;
+        self.level = level;
+        if sections <= 0 { return; }
+
+        for _ in 0..sections { self.splits.push(self.frame); }
+        self.emit(GameEvent::LevelUp { level });
+    }
+
+    pub fn set_practice(&mut self, practice: bool) -> Result<()> {
+        self.practice = if practice { Some(Practice::new()) } else { None };
+        self.restart()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let mut teto = self.teto.clone();
+        teto.reset();
+
+        Snapshot {
+            board: self.board.clone(),
+            teto,
+            next_teto: self.next_teto.clone(),
+            held_teto: self.held_teto.clone(),
+            tetos: self.tetos.clone(),
+            queue: self.practice.as_ref().map_or(Vec::new(), |practice| practice.queue.clone()),
+            can_hold: self.can_hold,
+            score: self.score,
+            level: self.level,
+            splits: self.splits.clone(),
+            statistics: self.statistics
+        }
+    }
+
+    fn load_snapshot(&mut self, snapshot: Snapshot) {
+        self.board = snapshot.board;
+        self.das = Das::new(to_frames(self.settings.das), to_frames(self.settings.arr));
+
+        self.teto = snapshot.teto;
+        self.next_teto = snapshot.next_teto;
+        self.held_teto = snapshot.held_teto;
+        self.tetos = snapshot.tetos;
+        if let Some(practice) = self.practice.as_mut() { practice.queue = snapshot.queue; }
+
+        self.can_hold = snapshot.can_hold;
+        self.spawn_frame = None;
+        self.score = snapshot.score;
+        self.level = snapshot.level;
+        self.splits = snapshot.splits;
+        self.statistics = snapshot.statistics;
+        self.soft_dropped = 0;
+        self.game_over = false;
+        self.complete = false;
+    }
+
+    pub fn save_position(&mut self) {
+        let snapshot = self.snapshot();
+        if let Some(practice) = self.practice.as_mut() { practice.save(snapshot); }
+    }
+
+    pub fn retry(&mut self) {
+        let Some(snapshot) = self.practice.as_ref().and_then(|practice| practice.get_saved()).cloned() else { return; };
+        self.load_snapshot(snapshot);
+        self.assisted = true;
+    }
+
+    pub fn undo(&mut self) {
+        let current = self.snapshot();
+        let Some(snapshot) = self.practice.as_mut().and_then(|practice| practice.undo(current)) else { return; };
+        self.load_snapshot(snapshot);
+        self.assisted = true;
+    }
+
+    pub fn redo(&mut self) {
+        let current = self.snapshot();
+        let Some(snapshot) = self.practice.as_mut().and_then(|practice| practice.redo(current)) else { return; };
+        self.load_snapshot(snapshot);
+    }
+
+    //Closing the editor swaps the current and next tetos for the start of the queue
+    pub fn set_editing(&mut self, editing: bool) -> Result<()> {
+        let Some(practice) = self.practice.as_mut() else { return Ok(()); };
+        practice.editing = editing;
+        if editing || practice.queue.is_empty() { return Ok(()); }
+
+        self.teto = Teto::new(practice.queue.remove(0), self.settings.rotation_system)?;
+        if !practice.queue.is_empty() { self.next_teto = Teto::new(practice.queue.remove(0), self.settings.rotation_system)?; }
+        self.spawn_frame = None;
+        self.can_hold = true;
+        Ok(())
+    }
+
+    pub fn paint(&mut self, i: i32, j: i32, erase: bool) {
+        let Some(practice) = self.practice.as_ref().filter(|practice| practice.editing) else { return; };
+        let cell = if erase { None } else { practice.brush };
+
+        self.board.set_cell(i, j, cell);
+        self.assisted = true;
+    }
+
+    pub fn get_practice(&self) -> Option<&Practice> {
+        self.practice.as_ref()
+    }
+
+    pub fn get_practice_mut(&mut self) -> Option<&mut Practice> {
+        self.practice.as_mut()
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.practice.as_ref().is_some_and(|practice| practice.editing)
+    }
+
+    fn add_score(&mut self, points: i32) {
+        if points == 0 { return; }
+        self.score += points;
+        self.emit(GameEvent::Scored { points });
+    }
+
+    //The game keeps its own statistics up to date, everything else hears about events once they're published
+    fn emit(&mut self, event: GameEvent) {
+        self.statistics.handle(&event);
+        self.events.emit(event);
+    }
+
+    pub fn publish_events(&mut self, subscribers: &mut [&mut dyn Subscriber]) {
+        self.events.publish(subscribers);
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn get_board_mut(&mut self) -> &mut Board {
+        &mut self.board
+    }
+
+    //None while waiting for the next teto to spawn
+    pub fn get_teto(&self) -> Option<&Teto> {
+        if self.spawn_frame.is_some() { None } else { Some(&self.teto) }
+    }
+
+    pub fn get_frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn get_next_teto(&self) -> &Teto {
+        &self.next_teto
+    }
+
+    pub fn get_held_teto(&self) -> Option<&Teto> {
+        self.held_teto.as_ref()
+    }
+
+    //What's left of the current bag
+    pub fn get_bag(&self) -> &[TetoType] {
+        &self.tetos
+    }
+
+    pub fn get_das(&self) -> &Das {
+        &self.das
+    }
+
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn get_statistics(&self) -> Statistics {
+        self.statistics
+    }
+
+    pub fn get_grade(&self) -> Option<&'static str> {
+        self.scoring.get().get_grade(self.score)
+    }
+
+    pub fn get_high_score(&self) -> i32 {
+        self.high_score
+    }
+
+    pub fn get_mode(&self) -> ModeType {
+        self.mode
+    }
+
+    pub fn get_level(&self) -> i32 {
+        self.level
+    }
+
+    pub fn get_splits(&self) -> &[u32] {
+        &self.splits
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
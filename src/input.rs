@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCcw,
+    RotateCw,
+    Rotate180,
+    Hold
+}
+
+pub const ACTIONS: [Action; 8] = [Action::MoveLeft, Action::MoveRight, Action::SoftDrop, Action::HardDrop, Action::RotateCcw, Action::RotateCw, Action::Rotate180, Action::Hold];
+
+//What the game sees of the player, frontends translate their own key events into this once per frame
+pub struct Input {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new()
+        }
+    }
+
+    pub fn update(&mut self, pressed: HashSet<Action>) {
+        self.just_pressed = pressed.difference(&self.pressed).copied().collect();
+        if pressed != self.pressed { log::trace!("Pressed {:?}, just pressed {:?}", pressed, self.just_pressed); }
+        self.pressed = pressed;
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn is_just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//Keys are named like ggez's KeyCode debug names ("A", "Space", "Left"), every frontend maps its own keys to these
+//Gamepad buttons are gilrs's Button debug names after "Pad" ("PadSouth", "PadDPadLeft") so they can't clash with keys
+pub struct Bindings {
+    keys: Vec<(String, Action)>
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Self {
+            keys: [("A", Action::MoveLeft), ("D", Action::MoveRight), ("S", Action::SoftDrop), ("Space", Action::HardDrop), ("Left", Action::RotateCcw), ("Right", Action::RotateCw), ("Up", Action::Rotate180), ("W", Action::Hold),
+                ("PadDPadLeft", Action::MoveLeft), ("PadDPadRight", Action::MoveRight), ("PadDPadDown", Action::SoftDrop), ("PadDPadUp", Action::HardDrop),
+                ("PadSouth", Action::RotateCw), ("PadEast", Action::RotateCcw), ("PadWest", Action::RotateCcw), ("PadNorth", Action::Rotate180), ("PadLeftTrigger", Action::Hold), ("PadRightTrigger", Action::Hold)]
+                .into_iter().map(|binding| (binding.0.to_owned(), binding.1)).collect()
+        }
+    }
+
+    //A key does one action, binding it again replaces what it did
+    pub fn bind(&mut self, key: &str, action: Action) {
+        self.unbind(key);
+        self.keys.push((key.to_owned(), action));
+    }
+
+    pub fn unbind(&mut self, key: &str) {
+        self.keys.retain(|binding| binding.0 != key);
+    }
+
+    pub fn set_keys(&mut self, action: Action, keys: &[&str]) {
+        self.keys.retain(|binding| binding.1 != action);
+        for key in keys { self.bind(key, action); }
+    }
+
+    //Replaces the keys of action on the same device as key, so remapping the keyboard keeps the gamepad working
+    pub fn rebind(&mut self, action: Action, key: &str) {
+        let is_gamepad = key.starts_with("Pad");
+        self.keys.retain(|binding| binding.1 != action || binding.0.starts_with("Pad") != is_gamepad);
+        self.bind(key, action);
+    }
+
+    pub fn get_keys(&self, action: Action) -> Vec<&str> {
+        self.keys.iter().filter(|binding| binding.1 == action).map(|binding| binding.0.as_str()).collect()
+    }
+
+    pub fn get_actions<'a>(&self, pressed_keys: impl IntoIterator<Item = &'a str>) -> HashSet<Action> {
+        let pressed_keys: Vec<&str> = pressed_keys.into_iter().collect();
+        self.keys.iter().filter(|binding| pressed_keys.contains(&binding.0.as_str())).map(|binding| binding.1).collect()
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
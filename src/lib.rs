@@ -0,0 +1,30 @@
+pub mod assets;
+pub mod board;
+pub mod das;
+pub mod error;
+pub mod events;
+pub mod fumen;
+pub mod game;
+pub mod input;
+pub mod logger;
+pub mod mode;
+pub mod practice;
+pub mod rotation;
+pub mod scoring;
+pub mod settings;
+pub mod teto;
+
+#[cfg(feature = "gui")]
+pub mod audio;
+#[cfg(feature = "gui")]
+pub mod effects;
+#[cfg(feature = "gui")]
+pub mod menu;
+#[cfg(feature = "gui")]
+pub mod popup;
+#[cfg(feature = "gui")]
+pub mod theme;
+#[cfg(feature = "gui")]
+pub mod touch;
+#[cfg(feature = "gui")]
+pub mod util;
@@ -0,0 +1,32 @@
+use std::{env, sync::OnceLock, time::Instant};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+//Writes log records to stderr with the seconds since the game started
+//The level comes from the TETRIS_LOG environment variable (off, error, warn, info, debug, trace) and is warn by default
+//Other crates (ggez, wgpu) only get through with warnings and errors, their debug output drowns out ours
+struct Logger {
+    start: OnceLock<Instant>
+}
+
+static LOGGER: Logger = Logger { start: OnceLock::new() };
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && (metadata.target().starts_with("tetris") || metadata.level() <= Level::Warn)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) { return; }
+        let time = self.start.get_or_init(Instant::now).elapsed().as_secs_f32();
+        eprintln!("[{:>9.3}s {:<5} {}] {}", time, record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    LOGGER.start.get_or_init(Instant::now);
+    let level = env::var("TETRIS_LOG").ok().and_then(|level| level.parse().ok()).unwrap_or(LevelFilter::Warn);
+    if log::set_logger(&LOGGER).is_ok() { log::set_max_level(level); }
+}
@@ -1,158 +1,607 @@
-use std::{env, fs};
+use std::env;
 
-use ggez::{event, glam::Vec2, graphics::{self, Canvas, Color, DrawParam, FontData, Rect}, input::keyboard::KeyCode, ContextBuilder, GameResult};
+use ggez::{conf::FullscreenType, event, glam::Vec2, graphics::{Canvas, Color, FontData, Rect, Sampler, Text}, input::{gamepad::{gilrs::Button, GamepadId}, keyboard::{KeyCode, KeyInput, KeyMods}, mouse::MouseButton}, Context, ContextBuilder, GameResult};
 
-use crate::{board::Board, teto::{Teto, TetoType}, util::{CELL_SIZE, SCREEN_SIZE, UI_SIZE}};
+use tetris::{assets, logger, audio::Audio, effects::Effects, fumen, menu::Menu, popup::ClearPopup, game::{self, Game, TICKS_PER_SECOND}, input::{Action, Bindings, Input, ACTIONS}, settings::{self, EffectToggles, Settings}, teto::{Teto, TetoType, ONE_G}, theme::Theme, touch::TouchControls, util::{self, CELL_SIZE, SCREEN_SIZE, UI_SIZE}};
 
-mod board;
-mod teto;
-mod util;
+//After a stall (dragging the window, a slow frame) the game slows down for a moment instead of running a burst of ticks
+const MAX_TICKS_PER_FRAME: u32 = 5;
 
-struct PlayState {
-    board: Board,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Title,
+    Playing,
+    Paused,
+    Settings,
+    Gameplay,
+    Display,
+    Controls
+}
 
-    teto: Teto,
-    next_teto: Teto,
-    held_teto: Option<Teto>,
-    tetos: Vec<TetoType>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuItem {
+    Play,
+    Practice,
+    Resume,
+    Restart,
+    Settings,
+    TitleScreen,
+    Quit,
+    Back,
+    Gameplay,
+    Display,
+    Controls,
+    Mode,
+    Rotation,
+    Scoring,
+    Gravity,
+    Das,
+    Arr,
+    Are,
+    LineClearDelay,
+    Theme,
+    Effects,
+    Fullscreen,
+    TouchControls,
+    Sound,
+    SfxVolume,
+    MusicVolume,
+    Bind(Action),
+    ResetControls
+}
 
-    can_hold: bool,
-    score: i32,
-    high_score: i32,
-    game_over: bool,
+fn get_action_name(action: Action) -> &'static str {
+    match action {
+        Action::MoveLeft => "MOVE LEFT",
+        Action::MoveRight => "MOVE RIGHT",
+        Action::SoftDrop => "SOFT DROP",
+        Action::HardDrop => "HARD DROP",
+        Action::RotateCcw => "ROTATE CCW",
+        Action::RotateCw => "ROTATE CW",
+        Action::Rotate180 => "ROTATE 180",
+        Action::Hold => "HOLD"
+    }
+}
 
+struct PlayState {
+    game: Game,
+    input: Input,
+    audio: Audio,
+    theme: Theme,
+    effects: Effects,
+    popup: ClearPopup,
+    touch_controls: TouchControls,
+
+    screen: Screen,
+    //Where the settings menu goes back to
+    settings_back: Screen,
+    menu_selected: usize,
+    //The action waiting for a key or button to be bound to it
+    rebinding: Option<Action>,
+    //Shown over everything until dismissed, the game keeps going after
+    error: Option<String>,
+    //Timing and piece state in the corner, toggled with F3
+    debug_overlay: bool,
+
+    screen_coordinates: Rect,
     score_ui_pos: Vec2,
     next_piece_ui_pos: Vec2,
     held_piece_ui_pos: Vec2
 }
 
 impl PlayState {
-    fn new() -> Self {
-        let mut tetos = Teto::get_teto_bag();
-        Self {
-            board: Board::new(),
+    fn new(ctx: &Context, settings: Settings) -> GameResult<Self> {
+        let mut audio = Audio::new(ctx, &settings);
+        audio.play_music(ctx);
+
+        Ok(Self {
+            input: Input::new(),
+            audio,
+            theme: Theme::load(ctx, &settings.theme),
+            effects: Effects::new(settings.effects),
+            popup: ClearPopup::new(),
+            touch_controls: TouchControls::new(),
+
+            screen: Screen::Title,
+            settings_back: Screen::Title,
+            menu_selected: 0,
+            rebinding: None,
+            error: None,
+            debug_overlay: false,
+
+            screen_coordinates: util::get_screen_coordinates(ctx.gfx.drawable_size().into()),
+            score_ui_pos: Vec2::new(UI_SIZE.x / 2.0, UI_SIZE.y / 2.0),
+            next_piece_ui_pos: Vec2::new(SCREEN_SIZE.x - UI_SIZE.x / 2.0, UI_SIZE.y / 2.0 - CELL_SIZE * 3.25),
+            held_piece_ui_pos: Vec2::new(SCREEN_SIZE.x - UI_SIZE.x / 2.0, UI_SIZE.y / 2.0 + CELL_SIZE * 2.75),
 
-            teto: Teto::new(tetos.swap_remove(rand::random_range(0..tetos.len()))),
-            next_teto: Teto::new(tetos.swap_remove(rand::random_range(0..tetos.len()))),
-            held_teto: None,
-            tetos: tetos,
+            game: Game::new(settings)?
+        })
+    }
 
-            can_hold: true,
-            score: 0,
-            high_score: fs::read_to_string("./res/high_score.txt").unwrap_or("0".to_owned()).parse().unwrap_or(0),
-            game_over: false,
+    fn next_theme(&mut self, ctx: &Context) {
+        let themes = Theme::list(ctx);
+        if themes.is_empty() { return; }
 
-            score_ui_pos: Vec2::new(UI_SIZE.x / 2.0, UI_SIZE.y / 2.0),
-            next_piece_ui_pos: Vec2::new(SCREEN_SIZE.x - UI_SIZE.x / 2.0, UI_SIZE.y / 2.0 - CELL_SIZE * 3.25),
-            held_piece_ui_pos: Vec2::new(SCREEN_SIZE.x - UI_SIZE.x / 2.0, UI_SIZE.y / 2.0 + CELL_SIZE * 2.75)
+        let i = themes.iter().position(|name| *name == self.game.settings.theme).map_or(0, |i| (i + 1) % themes.len());
+        self.game.settings.theme = themes[i].clone();
+        self.theme = Theme::load(ctx, &self.game.settings.theme);
+    }
+
+    fn restart(&mut self, ctx: &Context) -> GameResult {
+        let restarted = self.game.restart();
+        self.audio.play_music(ctx);
+        Ok(restarted?)
+    }
+
+    fn show_error(&mut self, message: String) {
+        log::error!("{}", message);
+        self.error = Some(message);
+    }
+
+    fn get_mouse_pos(&self, ctx: &Context) -> Vec2 {
+        util::get_screen_pos(self.screen_coordinates, ctx.gfx.drawable_size().into(), ctx.mouse.position().into())
+    }
+
+    fn set_screen(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.menu_selected = 0;
+    }
+
+    //What escape does, pausing and resuming or leaving the settings
+    fn back(&mut self) {
+        match self.screen {
+            Screen::Playing if !self.game.is_game_over() => self.set_screen(Screen::Paused),
+            Screen::Paused => self.set_screen(Screen::Playing),
+            Screen::Settings => {
+                self.save_settings();
+                self.set_screen(self.settings_back);
+            },
+            Screen::Gameplay | Screen::Display | Screen::Controls => self.set_screen(Screen::Settings),
+            _ => {}
         }
     }
 
-    //Returns the old teto, not the new one
-    fn pop_teto(&mut self) -> Teto {
-        if self.tetos.len() == 0 { self.tetos = Teto::get_teto_bag(); }
-        let new_teto = Teto::new(self.tetos.swap_remove(rand::random_range(0..self.tetos.len())));
-        std::mem::replace(&mut self.teto, std::mem::replace(&mut self.next_teto, new_teto))
+    fn save_settings(&mut self) {
+        if let Err(err) = self.game.settings.save() { self.show_error(format!("Couldn't save settings: {}", err)); }
+    }
+
+    fn rebind(&mut self, key: &str) {
+        if let Some(action) = self.rebinding.take() { self.game.settings.bindings.rebind(action, key); }
+    }
+
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) -> GameResult {
+        self.game.settings.fullscreen = !self.game.settings.fullscreen;
+        ctx.gfx.set_fullscreen(if self.game.settings.fullscreen { FullscreenType::Desktop } else { FullscreenType::Windowed })
+    }
+
+    fn toggle_effects(&mut self) {
+        self.game.settings.effects = EffectToggles::new(!self.game.settings.effects.any());
+        self.effects.set_toggles(self.game.settings.effects);
+        let row_collapse = self.game.settings.effects.row_collapse;
+        self.game.get_board_mut().set_animate_collapse(row_collapse);
     }
 
-    fn update_high_score(&mut self) {
-        if self.score > self.high_score {
-            self.high_score = self.score;
-            fs::write("./res/high_score.txt", self.score.to_string()).expect(&format!("Something went wrong when saving high score ({}).", self.high_score));
+    fn toggle_sound(&mut self) {
+        self.game.settings.muted = !self.game.settings.muted;
+        self.audio.set_muted(self.game.settings.muted);
+    }
+
+    //The menu of the current screen, playing only has one once the game is over
+    fn get_menu(&self) -> Option<Menu<MenuItem>> {
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        let item = |text: &str, item: MenuItem| (text.to_owned(), item);
+
+        let menu = match self.screen {
+            Screen::Title => Menu::new("TETRIS", vec![(format!("PLAY: {}", self.game.settings.mode.get().name()), MenuItem::Play), item("PRACTICE", MenuItem::Practice), item("SETTINGS", MenuItem::Settings), item("QUIT", MenuItem::Quit)], self.menu_selected),
+            Screen::Playing if self.game.is_game_over() => {
+                let mode = self.game.get_mode().get();
+                let title = if self.game.is_complete() { format!("{} CLEAR!", mode.name()) } else { "GAME OVER".to_owned() };
+                let statistics = self.game.get_statistics();
+                let mut text = vec![format!("SCORE: {}", self.game.get_score())];
+                if mode.has_levels() { text.push(format!("LEVEL: {}", self.game.get_level())); }
+                text.extend([format!("LINES: {}", statistics.lines), format!("PIECES: {}", statistics.pieces), format!("TIME: {}", game::format_frames(self.game.get_frame()))]);
+                //Section times two to a line, for modes with sections longer than a level
+                let section_size = mode.get_section_size();
+                if section_size > 1 {
+                    let splits = self.game.get_splits();
+                    let sections: Vec<String> = splits.iter().enumerate().map(|(n, split)| format!("{:03}-{:03} {}", n as i32 * section_size, (n as i32 + 1) * section_size - 1, game::format_frames(split - if n == 0 { 0 } else { splits[n - 1] }))).collect();
+                    text.extend(sections.chunks(2).map(|pair| pair.join("   ")));
+                }
+                Menu::new(&title, vec![item("RESTART", MenuItem::Restart), item("SETTINGS", MenuItem::Settings), item("TITLE SCREEN", MenuItem::TitleScreen), item("QUIT", MenuItem::Quit)], self.menu_selected).with_text(text)
+            },
+            Screen::Playing => return None,
+            Screen::Paused => Menu::new("PAUSED", vec![item("RESUME", MenuItem::Resume), item("RESTART", MenuItem::Restart), item("SETTINGS", MenuItem::Settings), item("TITLE SCREEN", MenuItem::TitleScreen), item("QUIT", MenuItem::Quit)], self.menu_selected),
+            Screen::Settings => {
+                let mut items = Vec::new();
+                //Changing these mid game would change the rules of a game in progress
+                if self.settings_back != Screen::Paused { items.push(item("GAMEPLAY", MenuItem::Gameplay)); }
+                items.extend([item("VIDEO & AUDIO", MenuItem::Display), item("CONTROLS", MenuItem::Controls), item("BACK", MenuItem::Back)]);
+                Menu::new("SETTINGS", items, self.menu_selected)
+            },
+            Screen::Gameplay => {
+                let settings = &self.game.settings;
+                //Modes with their own delays ignore these settings
+                let by_section = settings.mode.get().get_timings(0).is_some();
+                Menu::new("GAMEPLAY", vec![
+                    (format!("MODE: {}", settings.mode.get().name()), MenuItem::Mode),
+                    (format!("ROTATION: {}", settings.rotation_system.get().name()), MenuItem::Rotation),
+                    (format!("SCORING: {}", settings.scoring.get().name()), MenuItem::Scoring),
+                    (if settings.mode.get().has_levels() { "GRAVITY: BY LEVEL".to_owned() } else { format!("GRAVITY: {:.2}S", settings.fall_duration) }, MenuItem::Gravity),
+                    (format!("DAS: {:.2}S", settings.das), MenuItem::Das),
+                    (format!("ARR: {:.2}S", settings.arr), MenuItem::Arr),
+                    (if by_section { "ARE: BY SECTION".to_owned() } else { format!("ARE: {:.2}S", settings.are) }, MenuItem::Are),
+                    (if by_section { "LINE CLEAR DELAY: BY SECTION".to_owned() } else { format!("LINE CLEAR DELAY: {:.2}S", settings.line_clear_delay) }, MenuItem::LineClearDelay),
+                    item("BACK", MenuItem::Back)
+                ], self.menu_selected)
+            },
+            Screen::Display => {
+                let settings = &self.game.settings;
+                Menu::new("VIDEO & AUDIO", vec![
+                    (format!("THEME: {}", self.theme.name), MenuItem::Theme),
+                    (format!("EFFECTS: {}", on_off(settings.effects.any())), MenuItem::Effects),
+                    (format!("FULLSCREEN: {}", on_off(settings.fullscreen)), MenuItem::Fullscreen),
+                    (format!("TOUCH CONTROLS: {}", on_off(settings.touch_controls)), MenuItem::TouchControls),
+                    (format!("SOUND: {}", on_off(!settings.muted)), MenuItem::Sound),
+                    (format!("SFX VOLUME: {:.0}%", settings.sfx_volume * 100.0), MenuItem::SfxVolume),
+                    (format!("MUSIC VOLUME: {:.0}%", settings.music_volume * 100.0), MenuItem::MusicVolume),
+                    item("BACK", MenuItem::Back)
+                ], self.menu_selected)
+            },
+            Screen::Controls => {
+                //Keyboard keys first, then gamepad buttons without their prefix
+                let get_keys = |action: Action| {
+                    let keys = self.game.settings.bindings.get_keys(action);
+                    let keyboard: Vec<&str> = keys.iter().filter(|key| !key.starts_with("Pad")).copied().collect();
+                    let gamepad: Vec<&str> = keys.iter().filter_map(|key| key.strip_prefix("Pad")).collect();
+                    format!("{} / {}", keyboard.join(" "), gamepad.join(" ")).to_uppercase()
+                };
+                let mut items: Vec<(String, MenuItem)> = ACTIONS.into_iter().map(|action| (format!("{}: {}", get_action_name(action), get_keys(action)), MenuItem::Bind(action))).collect();
+                items.extend([item("RESET TO DEFAULTS", MenuItem::ResetControls), item("BACK", MenuItem::Back)]);
+                let title = self.rebinding.map_or("CONTROLS".to_owned(), |action| format!("PRESS A KEY FOR {}", get_action_name(action)));
+                Menu::new(&title, items, self.menu_selected)
+            }
+        };
+        Some(menu)
+    }
+
+    //dir is 0 when the item was clicked or entered, -1 or 1 for left and right which only change values
+    fn choose(&mut self, ctx: &mut Context, item: MenuItem, dir: i32) -> GameResult {
+        let step = if dir == 0 { 1 } else { dir };
+        let settings = &mut self.game.settings;
+        match item {
+            MenuItem::Mode => settings.mode = settings.mode.next(),
+            MenuItem::Play if dir != 0 => settings.mode = settings.mode.next(),
+            MenuItem::Rotation => settings.rotation_system = settings.rotation_system.next(),
+            MenuItem::Scoring => settings.scoring = settings.scoring.next(),
+            MenuItem::Gravity => settings.fall_duration = settings::FALL_DURATION_RANGE.step(settings.fall_duration, step),
+            MenuItem::Das => settings.das = settings::DAS_RANGE.step(settings.das, step),
+            MenuItem::Arr => settings.arr = settings::ARR_RANGE.step(settings.arr, step),
+            MenuItem::Are => settings.are = settings::ARE_RANGE.step(settings.are, step),
+            MenuItem::LineClearDelay => settings.line_clear_delay = settings::LINE_CLEAR_DELAY_RANGE.step(settings.line_clear_delay, step),
+            MenuItem::Theme => self.next_theme(ctx),
+            MenuItem::SfxVolume | MenuItem::MusicVolume => {
+                if item == MenuItem::SfxVolume { settings.sfx_volume = settings::VOLUME_RANGE.step(settings.sfx_volume, step); } else { settings.music_volume = settings::VOLUME_RANGE.step(settings.music_volume, step); }
+                self.audio.set_volumes(settings.sfx_volume, settings.music_volume);
+            },
+            _ if dir != 0 => {},
+            _ => self.activate(ctx, item)?
+        }
+        Ok(())
+    }
+
+    //Buttons that do something instead of changing a value
+    fn activate(&mut self, ctx: &mut Context, item: MenuItem) -> GameResult {
+        match item {
+            MenuItem::Play | MenuItem::Practice => {
+                let started = self.game.set_practice(item == MenuItem::Practice);
+                self.audio.play_music(ctx);
+                started?;
+                self.set_screen(Screen::Playing);
+            },
+            MenuItem::Resume => self.set_screen(Screen::Playing),
+            MenuItem::Restart => {
+                self.set_screen(Screen::Playing);
+                self.restart(ctx)?;
+            },
+            MenuItem::Settings => {
+                self.settings_back = self.screen;
+                self.set_screen(Screen::Settings);
+            },
+            MenuItem::TitleScreen => {
+                self.set_screen(Screen::Title);
+                self.game.update_high_score()?;
+            },
+            MenuItem::Quit => ctx.request_quit(),
+            MenuItem::Back => self.back(),
+            MenuItem::Gameplay => self.set_screen(Screen::Gameplay),
+            MenuItem::Display => self.set_screen(Screen::Display),
+            MenuItem::Controls => self.set_screen(Screen::Controls),
+            MenuItem::Effects => self.toggle_effects(),
+            MenuItem::Fullscreen => self.toggle_fullscreen(ctx)?,
+            MenuItem::TouchControls => self.game.settings.touch_controls = !self.game.settings.touch_controls,
+            MenuItem::Sound => self.toggle_sound(),
+            MenuItem::Bind(action) => self.rebinding = Some(action),
+            MenuItem::ResetControls => self.game.settings.bindings = Bindings::new(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn update_practice(&mut self, ctx: &Context) -> GameResult {
+        if ctx.keyboard.is_key_just_pressed(KeyCode::P) {
+            let started = self.game.set_practice(self.game.get_practice().is_none());
+            self.audio.play_music(ctx);
+            started?;
+        }
+
+        let Some(practice) = self.game.get_practice_mut() else { return Ok(()); };
+        if ctx.keyboard.is_key_just_pressed(KeyCode::G) { practice.gravity = !practice.gravity; }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::F5) { self.game.save_position(); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::F9) { self.game.retry(); }
+        if ctx.keyboard.active_mods().contains(KeyMods::CTRL) {
+            if ctx.keyboard.is_key_just_pressed(KeyCode::Z) { self.game.undo(); }
+            if ctx.keyboard.is_key_just_pressed(KeyCode::Y) { self.game.redo(); }
         }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::E) { self.game.set_editing(!self.game.is_editing())?; }
+
+        if self.game.is_editing() { self.update_editor(ctx); }
+        Ok(())
     }
 
-    fn restart(&mut self) {
-        self.update_high_score();
+    fn update_editor(&mut self, ctx: &Context) {
+        let brushes = Teto::get_teto_bag().into_iter().chain([TetoType::Garbage]);
+        let brush_keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8];
+
+        let Some(practice) = self.game.get_practice_mut() else { return; };
+        for (key, brush) in brush_keys.into_iter().zip(brushes) {
+            if ctx.keyboard.is_key_just_pressed(key) { practice.brush = Some(brush); }
+        }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::N) && let Some(brush) = practice.brush.filter(|brush| *brush != TetoType::Garbage) { practice.queue.push(brush); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Back) { practice.queue.pop(); }
 
-        self.board = Board::new();
+        let paint = ctx.mouse.button_pressed(MouseButton::Left);
+        let erase = ctx.mouse.button_pressed(MouseButton::Right);
+        if (paint || erase) && let Some(cell) = util::get_cell_at(self.screen_coordinates, ctx.gfx.drawable_size().into(), ctx.mouse.position().into()) { self.game.paint(cell.0, cell.1, erase); }
+    }
+
+    fn draw_practice(&self, canvas: &mut Canvas) {
+        let Some(practice) = self.game.get_practice() else { return; };
+
+        let mut lines = vec!["PRACTICE (P)".to_owned(), format!("GRAVITY: {} (G)", if practice.gravity { "ON" } else { "OFF" }), "SAVE (F5) RETRY (F9)".to_owned(), "UNDO/REDO: CTRL+Z/Y".to_owned(), format!("EDITOR: {} (E)", if practice.editing { "ON" } else { "OFF" })];
+        if practice.editing {
+            lines.push(format!("BRUSH: {} (1-8)", practice.brush.map_or("NONE".to_owned(), |brush| format!("{:?}", brush).to_uppercase())));
+            lines.push(format!("QUEUE: {} (N/BKSP)", practice.queue.iter().map(|teto_type| format!("{:?}", teto_type)).collect::<String>()));
+            lines.push("PAINT/ERASE: MOUSE L/R".to_owned());
+        }
+
+        for (n, line) in lines.iter().enumerate() {
+            util::draw_text_centered_on(canvas, line, self.score_ui_pos + Vec2::Y * CELL_SIZE * (4.5 + n as f32 * 0.7), CELL_SIZE * 0.4, self.theme.text);
+        }
+    }
 
-        self.tetos = Teto::get_teto_bag();
-        self.teto = Teto::new(self.tetos.swap_remove(rand::random_range(0..self.tetos.len())));
-        self.next_teto = Teto::new(self.tetos.swap_remove(rand::random_range(0..self.tetos.len())));
-        self.held_teto = None;
+    fn draw_debug_overlay(&self, ctx: &Context, canvas: &mut Canvas) {
+        let das = self.game.get_das();
+
+        let mut lines = vec![format!("FPS:     {:.0}", ctx.time.fps()), format!("FRAME:   {:.2}MS", ctx.time.delta().as_secs_f32() * 1000.0), format!("TICK:    {}", self.game.get_frame())];
+        match self.game.get_teto() {
+            Some(teto) => {
+                let (i, j) = teto.get_pos();
+                lines.push(format!("PIECE:   {:?} ROT {} I {} J {}", teto.get_type(), teto.get_rot_state(), i, j));
+                lines.push(format!("GRAVITY: {:.3}G {:.0}%", teto.get_fall_speed() as f32 / ONE_G as f32, teto.get_fall_progress() as f32 / ONE_G as f32 * 100.0));
+                lines.push(format!("LOCK:    {}/{}T", teto.get_lock_frames(), teto.get_lock_delay()));
+            },
+            None => lines.push("PIECE:   SPAWNING".to_owned())
+        }
+        lines.push(format!("DAS:     L {} R {}/{}T", das.get_charge(Action::MoveLeft), das.get_charge(Action::MoveRight), das.get_delay()));
+        lines.push(format!("BAG:     {}", self.game.get_bag().iter().map(|teto_type| format!("{:?}", teto_type)).collect::<String>()));
 
-        self.can_hold = true;
-        self.score = 0;
-        self.game_over = false;
+        util::draw_rect(canvas, 0.0, 0.0, UI_SIZE.x, CELL_SIZE * (lines.len() as f32 * 0.5 + 0.5), Color::new(0.0, 0.0, 0.0, 0.8));
+        for (n, line) in lines.iter().enumerate() {
+            util::draw(canvas, Text::new(line.as_str()).set_font("font").set_scale(CELL_SIZE * 0.35), Vec2::new(CELL_SIZE * 0.25, CELL_SIZE * (0.25 + n as f32 * 0.5)), Color::WHITE);
+        }
     }
 }
 
 impl event::EventHandler<ggez::GameError> for PlayState {
     fn update(&mut self, ctx: &mut ggez::Context) -> Result<(), ggez::GameError> {
-        if self.game_over {
-            if ctx.keyboard.is_key_just_released(KeyCode::R) { self.restart(); }
+        //Ticks due since the last frame are taken even when the game isn't running, or they would all run at once when it starts again
+        let mut ticks = 0;
+        while ctx.time.check_update_time(TICKS_PER_SECOND) { ticks += 1; }
+
+        if self.error.is_some() {
+            if ctx.keyboard.is_key_just_pressed(KeyCode::Return) || ctx.keyboard.is_key_just_pressed(KeyCode::Escape) || ctx.mouse.button_just_released(MouseButton::Left) { self.error = None; }
             return Ok(());
         }
 
-        if self.can_hold && ctx.keyboard.is_key_just_pressed(KeyCode::W) {
-            match self.held_teto.as_mut() {
-                None => self.held_teto = Some(self.pop_teto()),
-                Some(teto) => {
-                    std::mem::swap(teto, &mut self.teto);
-                    self.teto.reset();
-                    self.can_hold = false;
-                }
-            }
-            self.held_teto.as_mut().unwrap().reset();
+        if ctx.keyboard.is_key_just_pressed(KeyCode::F11) { self.toggle_fullscreen(ctx)?; }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::F4) { self.toggle_effects(); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::F3) { self.debug_overlay = !self.debug_overlay; }
+
+        if ctx.keyboard.is_key_just_pressed(KeyCode::F2) && let Err(err) = self.game.save_fumen() { self.show_error(format!("Couldn't save fumen: {}", err)); }
+
+        self.effects.update(ctx.time.delta().as_secs_f32());
+        self.popup.update(ctx.time.delta().as_secs_f32());
+
+        if ctx.keyboard.is_key_just_pressed(KeyCode::M) { self.toggle_sound(); }
+
+        if self.rebinding.is_some() {
+            if ctx.keyboard.is_key_just_pressed(KeyCode::Escape) { self.rebinding = None; }
+            else if let Some(key) = ctx.keyboard.pressed_keys().iter().find(|key| ctx.keyboard.is_key_just_pressed(**key)) { self.rebind(&format!("{:?}", key)); }
+            return Ok(());
         }
 
-        self.board.update(ctx);
-        self.teto.update(ctx, &self.board);
+        if let Some(mut menu) = self.get_menu() {
+            let chosen = menu.update(ctx, self.get_mouse_pos(ctx));
+            self.menu_selected = menu.get_selected();
+            if let Some((item, dir)) = chosen { self.choose(ctx, item, dir)?; }
+        }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Escape) { self.back(); }
+
+        if self.screen != Screen::Playing { return Ok(()); }
+
+        self.update_practice(ctx)?;
 
-        if self.teto.is_dead() {
-            let teto = self.pop_teto();
-            self.board.add(ctx, teto, &mut self.score, &mut self.game_over);
-            self.can_hold = true;
+        if self.game.is_game_over() {
+            if ctx.keyboard.is_key_just_released(KeyCode::R) { self.restart(ctx)?; }
+            if ctx.keyboard.is_key_just_pressed(KeyCode::Tab) { self.game.settings.rotation_system = self.game.settings.rotation_system.next(); }
+            if ctx.keyboard.is_key_just_pressed(KeyCode::X) { self.game.settings.scoring = self.game.settings.scoring.next(); }
+            if ctx.keyboard.is_key_just_pressed(KeyCode::C) { self.next_theme(ctx); }
+            return Ok(());
+        }
+
+        let pressed_keys: Vec<String> = ctx.keyboard.pressed_keys().iter().map(|key| format!("{:?}", key)).chain(util::get_pressed_buttons(ctx)).collect();
+        let mut actions = self.game.settings.bindings.get_actions(pressed_keys.iter().map(|key| key.as_str()));
+        if self.game.settings.touch_controls { actions.extend(self.touch_controls.get_actions(ctx, self.get_mouse_pos(ctx))); }
+        for _ in 0..ticks.min(MAX_TICKS_PER_FRAME) {
+            self.input.update(actions.clone());
+            self.game.update(&self.input)?;
         }
 
+        self.game.publish_events(&mut [&mut self.audio, &mut self.effects, &mut self.popup]);
+        self.audio.update(ctx);
+
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> Result<(), ggez::GameError> {
-        let mut canvas = Canvas::from_frame(ctx, Color::new(0.25, 0.25, 0.25, 1.0));
+        let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+        let shake = self.effects.get_shake_offset();
+        canvas.set_screen_coordinates(Rect::new(self.screen_coordinates.x + shake.x, self.screen_coordinates.y + shake.y, self.screen_coordinates.w, self.screen_coordinates.h));
+        canvas.set_sampler(Sampler::nearest_clamp());
+        util::draw_rect(&mut canvas, 0.0, 0.0, SCREEN_SIZE.x, SCREEN_SIZE.y, self.theme.background);
+
+        self.game.get_board().draw(self.game.get_frame(), &mut canvas, &self.theme);
+        if let Some(teto) = self.game.get_teto() { teto.draw(&mut canvas, self.game.get_board(), &self.theme); }
+        self.effects.draw(&mut canvas, &self.theme);
+        self.popup.draw(&mut canvas, &self.theme);
+
+        let score = self.game.get_score().to_string();
+        let combo = self.game.get_board().get_combo().to_string();
+        let high = self.game.get_high_score().to_string();
+        let perfect_clears = self.game.get_statistics().perfect_clears.to_string();
+        let lines = self.game.get_statistics().lines.to_string();
+        let level = self.game.get_level().to_string();
+        let len = score.len().max(combo.len()).max(high.len()).max(perfect_clears.len()).max(lines.len()).max(level.len());
+
+        if self.game.get_mode().get().has_levels() {
+            util::draw_text_centered_on(&mut canvas, &format!("TIME: {}", game::format_frames(self.game.get_frame())), self.score_ui_pos - Vec2::Y * CELL_SIZE * 7.5, CELL_SIZE * 0.75, self.theme.text);
+            util::draw_text_centered_on(&mut canvas, &format!("LEVEL: {:>len$}", level), self.score_ui_pos - Vec2::Y * CELL_SIZE * 6.0, CELL_SIZE * 0.75, self.theme.text);
+        }
+        util::draw_text_centered_on(&mut canvas, &format!("LINES: {:>len$}", lines), self.score_ui_pos - Vec2::Y * CELL_SIZE * 4.5, CELL_SIZE * 0.75, self.theme.text);
 
-        self.board.draw(&ctx, &mut canvas);
-        self.teto.draw(&mut canvas, &self.board);
+        if let Some(grade) = self.game.get_grade() { util::draw_text_centered_on(&mut canvas, &format!("GRADE: {:>len$}", grade), self.score_ui_pos - Vec2::Y * CELL_SIZE * 3.0, CELL_SIZE * 0.75, self.theme.text); }
+        util::draw_text_centered_on(&mut canvas, &format!("SCORE: {:>len$}", score), self.score_ui_pos - Vec2::Y * CELL_SIZE * 1.5, CELL_SIZE * 0.75, self.theme.text);
+        util::draw_text_centered_on(&mut canvas, &format!("COMBO: {:>len$}", combo), self.score_ui_pos, CELL_SIZE * 0.75, self.theme.text);
+        util::draw_text_centered_on(&mut canvas, &format!("HIGH:  {:>len$}", high), self.score_ui_pos + Vec2::Y * CELL_SIZE * 1.5, CELL_SIZE * 0.75, self.theme.text);
+        util::draw_text_centered_on(&mut canvas, &format!("PC:    {:>len$}", perfect_clears), self.score_ui_pos + Vec2::Y * CELL_SIZE * 3.0, CELL_SIZE * 0.75, self.theme.text);
 
-        let score = self.score.to_string();
-        let combo = self.board.get_combo().to_string();
-        let high = self.high_score.to_string();
-        let len = score.len().max(combo.len()).max(high.len());
+        self.draw_practice(&mut canvas);
 
-        util::draw_text_centered_on(&mut canvas, &format!("SCORE: {:>len$}", score), self.score_ui_pos - Vec2::Y * CELL_SIZE * 1.5, CELL_SIZE * 0.75, Color::WHITE);
-        util::draw_text_centered_on(&mut canvas, &format!("COMBO: {:>len$}", combo), self.score_ui_pos, CELL_SIZE * 0.75, Color::WHITE);
-        util::draw_text_centered_on(&mut canvas, &format!("HIGH:  {:>len$}", high), self.score_ui_pos + Vec2::Y * CELL_SIZE * 1.5, CELL_SIZE * 0.75, Color::WHITE);
+        util::draw_text_centered_on(&mut canvas, "NEXT", self.next_piece_ui_pos - Vec2::Y * CELL_SIZE, CELL_SIZE * 0.75, self.theme.text);
+        self.game.get_next_teto().draw_centered_at(&mut canvas, &self.theme, self.next_piece_ui_pos.x, self.next_piece_ui_pos.y);
 
-        util::draw_text_centered_on(&mut canvas, "NEXT", self.next_piece_ui_pos - Vec2::Y * CELL_SIZE, CELL_SIZE * 0.75, Color::WHITE);
-        self.next_teto.draw_centered_at(&mut canvas, self.next_piece_ui_pos.x, self.next_piece_ui_pos.y);
+        util::draw_text_centered_on(&mut canvas, "HOLD", self.held_piece_ui_pos - Vec2::Y * CELL_SIZE, CELL_SIZE * 0.75, self.theme.text);
+        if let Some(held_teto) = self.game.get_held_teto() { held_teto.draw_centered_at(&mut canvas, &self.theme, self.held_piece_ui_pos.x, self.held_piece_ui_pos.y); }
 
-        util::draw_text_centered_on(&mut canvas, "HOLD", self.held_piece_ui_pos - Vec2::Y * CELL_SIZE, CELL_SIZE * 0.75, Color::WHITE);
-        if let Some(held_teto) = &self.held_teto { held_teto.draw_centered_at(&mut canvas, self.held_piece_ui_pos.x, self.held_piece_ui_pos.y); }
+        if self.screen == Screen::Playing && !self.game.is_game_over() && self.game.settings.touch_controls { self.touch_controls.draw(&mut canvas, &self.theme, &self.input); }
+        if let Some(menu) = self.get_menu() { menu.draw(&mut canvas, &self.theme); }
+        if self.debug_overlay { self.draw_debug_overlay(ctx, &mut canvas); }
+        if let Some(error) = &self.error { util::draw_error(&mut canvas, error, "PRESS ENTER TO CONTINUE"); }
 
-        if self.game_over {
-            canvas.draw(&graphics::Quad, DrawParam::default().dest_rect(Rect::new(0.0, 0.0, SCREEN_SIZE.x, SCREEN_SIZE.y)).color(Color::new(0.0, 0.0, 0.0, 0.9)));
-            util::draw_text_centered_on_screen(&mut canvas, &format!("SCORE: {}", score), CELL_SIZE, Color::WHITE);
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn resize_event(&mut self, _ctx: &mut ggez::Context, width: f32, height: f32) -> Result<(), ggez::GameError> {
+        self.screen_coordinates = util::get_screen_coordinates(Vec2::new(width, height));
+        Ok(())
+    }
+
+    //Start pauses and resumes, on the game over screen start and select do what R and Tab do
+    fn gamepad_button_down_event(&mut self, ctx: &mut ggez::Context, button: Button, _id: GamepadId) -> Result<(), ggez::GameError> {
+        if self.rebinding.is_some() {
+            self.rebind(&format!("Pad{:?}", button));
+            return Ok(());
         }
 
-        canvas.finish(ctx)?;
+        match (self.screen, button) {
+            (Screen::Title, Button::Start) => self.activate(ctx, MenuItem::Play)?,
+            (Screen::Playing, Button::Start) if self.game.is_game_over() => self.restart(ctx)?,
+            (Screen::Playing, Button::Select) if self.game.is_game_over() => self.game.settings.rotation_system = self.game.settings.rotation_system.next(),
+            (Screen::Playing | Screen::Paused, Button::Start) => self.back(),
+            _ => {}
+        }
         Ok(())
     }
 
+    //Escape pauses instead of quitting, quitting is in the menus
+    fn key_down_event(&mut self, _ctx: &mut ggez::Context, _input: KeyInput, _repeated: bool) -> Result<(), ggez::GameError> {
+        Ok(())
+    }
+
+    //Errors go on the error screen instead of closing the game
+    fn on_error(&mut self, _ctx: &mut ggez::Context, _origin: event::ErrorOrigin, err: ggez::GameError) -> bool {
+        self.show_error(get_error_message(err));
+        false
+    }
+
+    //Nothing can be shown anymore, so errors while quitting only go to the log
     fn quit_event(&mut self, _ctx: &mut ggez::Context) -> Result<bool, ggez::GameError> {
-        self.update_high_score();
+        if let Err(err) = self.game.update_high_score() { log::error!("Couldn't save high score: {}", err); }
+        if let Err(err) = self.game.settings.save() { log::error!("Couldn't save settings: {}", err); }
         Ok(false)
     }
 }
 
+//Our own errors come through ggez as custom errors, their message is all the player needs
+fn get_error_message(err: ggez::GameError) -> String {
+    if let ggez::GameError::CustomError(message) = err { message } else { err.to_string() }
+}
+
+//Shown instead of the game when it can't start
+struct ErrorScreen {
+    message: String,
+    screen_coordinates: Rect
+}
+
+impl event::EventHandler<ggez::GameError> for ErrorScreen {
+    fn update(&mut self, ctx: &mut ggez::Context) -> Result<(), ggez::GameError> {
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Return) || ctx.mouse.button_just_released(MouseButton::Left) { ctx.request_quit(); }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut ggez::Context) -> Result<(), ggez::GameError> {
+        let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+        canvas.set_screen_coordinates(self.screen_coordinates);
+        util::draw_error(&mut canvas, &self.message, "PRESS ENTER TO QUIT");
+        canvas.finish(ctx)
+    }
+
+    fn resize_event(&mut self, _ctx: &mut ggez::Context, width: f32, height: f32) -> Result<(), ggez::GameError> {
+        self.screen_coordinates = util::get_screen_coordinates(Vec2::new(width, height));
+        Ok(())
+    }
+}
+
 fn main() -> GameResult {
     unsafe { env::set_var("RUST_BACKTRACE", "1") };
-    let (mut ctx, event_loop) = ContextBuilder::new("tetris", "ikeidjd")
+    logger::init();
+    let mut settings = Settings::load();
+    settings.fumen = fumen::from_args();
+
+    let mut builder = ContextBuilder::new("tetris", "ikeidjd")
                                 .window_setup(ggez::conf::WindowSetup::default().title("Tetris"))
-                                .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_SIZE.x, SCREEN_SIZE.y))
-                                .add_resource_path("./res")
-                                .build()?;
-    ctx.gfx.add_font("font", FontData::from_path(&ctx, "/PixelOperatorMono8-Bold.ttf")?);
-    let state = PlayState::new();
-    event::run(ctx, event_loop, state)
-}
\ No newline at end of file
+                                .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_SIZE.x, SCREEN_SIZE.y).resizable(true).min_dimensions(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0).fullscreen_type(if settings.fullscreen { FullscreenType::Desktop } else { FullscreenType::Windowed }));
+    if let Some(dir) = assets::get_res_dir() { builder = builder.add_resource_path(dir); }
+    let (mut ctx, event_loop) = builder.build()?;
+    ctx.gfx.add_font("font", FontData::from_slice(assets::FONT)?);
+    match PlayState::new(&ctx, settings) {
+        Ok(state) => event::run(ctx, event_loop, state),
+        Err(err) => {
+            let message = get_error_message(err);
+            log::error!("{}", message);
+            let screen_coordinates = util::get_screen_coordinates(ctx.gfx.drawable_size().into());
+            event::run(ctx, event_loop, ErrorScreen { message, screen_coordinates })
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use ggez::{glam::Vec2, graphics::{Canvas, Color, Rect}, input::{keyboard::KeyCode, mouse::MouseButton}, Context};
+
+use crate::{theme::Theme, util::{self, CELL_SIZE, SCREEN_SIZE}};
+
+const BUTTON_SIZE: Vec2 = Vec2 { x: CELL_SIZE * 14.0, y: CELL_SIZE * 1.2 };
+const BUTTON_SPACING: f32 = CELL_SIZE * 1.5;
+const TEXT_SPACING: f32 = CELL_SIZE * 0.8;
+
+//A column of buttons that can be clicked, tapped or picked with the arrow keys and enter
+//Left and right change the value of the selected button, for the buttons that have one
+pub struct Menu<T: Copy> {
+    title: String,
+    //Lines shown between the title and the buttons
+    text: Vec<String>,
+    items: Vec<(String, T)>,
+    selected: usize
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(title: &str, items: Vec<(String, T)>, selected: usize) -> Self {
+        Self {
+            title: title.to_owned(),
+            text: Vec::new(),
+            selected: selected.min(items.len().saturating_sub(1)),
+            items
+        }
+    }
+
+    pub fn with_text(mut self, text: Vec<String>) -> Self {
+        self.text = text;
+        self
+    }
+
+    fn get_text_height(&self) -> f32 {
+        if self.text.is_empty() { 0.0 } else { TEXT_SPACING * self.text.len() as f32 + CELL_SIZE * 0.5 }
+    }
+
+    fn get_button_rect(&self, n: usize) -> Rect {
+        let top = SCREEN_SIZE.y / 2.0 - (BUTTON_SPACING * (self.items.len() as f32 - 1.0) - self.get_text_height()) / 2.0 + CELL_SIZE;
+        Rect::new((SCREEN_SIZE.x - BUTTON_SIZE.x) / 2.0, top + BUTTON_SPACING * n as f32 - BUTTON_SIZE.y / 2.0, BUTTON_SIZE.x, BUTTON_SIZE.y)
+    }
+
+    fn get_button_at(&self, pos: Vec2) -> Option<usize> {
+        (0..self.items.len()).find(|n| self.get_button_rect(*n).contains(pos))
+    }
+
+    //mouse_pos is in SCREEN_SIZE coordinates, returns the item that was chosen this frame with 0, or with -1 and 1 for left and right
+    pub fn update(&mut self, ctx: &Context, mouse_pos: Vec2) -> Option<(T, i32)> {
+        if self.items.is_empty() { return None; }
+
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Up) { self.selected = (self.selected + self.items.len() - 1) % self.items.len(); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Down) { self.selected = (self.selected + 1) % self.items.len(); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Return) { return Some((self.items[self.selected].1, 0)); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Left) { return Some((self.items[self.selected].1, -1)); }
+        if ctx.keyboard.is_key_just_pressed(KeyCode::Right) { return Some((self.items[self.selected].1, 1)); }
+
+        //Only a moving mouse takes the selection, so it doesn't fight the keys while resting on a button
+        let hovered = self.get_button_at(mouse_pos);
+        let delta = ctx.mouse.delta();
+        if (delta.x != 0.0 || delta.y != 0.0) && let Some(n) = hovered { self.selected = n; }
+        if ctx.mouse.button_just_released(MouseButton::Left) && let Some(n) = hovered { return Some((self.items[n].1, 0)); }
+
+        None
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, theme: &Theme) {
+        util::draw_rect(canvas, 0.0, 0.0, SCREEN_SIZE.x, SCREEN_SIZE.y, Color::new(0.0, 0.0, 0.0, 0.9));
+        let text_top = self.get_button_rect(0).y - self.get_text_height();
+        util::draw_text_centered_on(canvas, &self.title, Vec2::new(SCREEN_SIZE.x / 2.0, text_top - CELL_SIZE * 1.5), CELL_SIZE, Color::WHITE);
+        for (n, line) in self.text.iter().enumerate() {
+            util::draw_text_centered_on(canvas, line, Vec2::new(SCREEN_SIZE.x / 2.0, text_top + TEXT_SPACING * (n as f32 + 0.5)), CELL_SIZE * 0.5, Color::WHITE);
+        }
+
+        for (n, item) in self.items.iter().enumerate() {
+            let rect = self.get_button_rect(n);
+            let (background, text) = if n == self.selected { (Color::WHITE, Color::BLACK) } else { (theme.background, Color::WHITE) };
+            util::draw_rect(canvas, rect.x, rect.y, rect.w, rect.h, background);
+            util::draw_text_centered_on(canvas, &item.0, rect.center().into(), CELL_SIZE * 0.45, text);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{game::{Statistics, TICKS_PER_SECOND}, teto::ONE_G};
+
+//Delays in ticks for modes that set their own instead of using the ones from the settings
+pub struct Timings {
+    pub are: u32,
+    pub line_clear_delay: u32,
+    pub lock_delay: u32
+}
+
+//How fast a game gets and when it ends
+pub trait GameMode {
+    fn name(&self) -> &'static str;
+
+    //Modes without levels play at the gravity from the settings the whole game
+    fn has_levels(&self) -> bool;
+
+    fn get_start_level(&self) -> i32 {
+        1
+    }
+
+    //The level once a new teto spawns, for modes where every piece counts
+    fn get_spawn_level(&self, level: i32) -> i32 {
+        level
+    }
+
+    //The level once lines were cleared, statistics already count them
+    fn get_clear_level(&self, level: i32, lines: i32, statistics: &Statistics) -> i32;
+
+    //Levels in a section, the game keeps the time each section was finished in and flashes when a new one starts
+    fn get_section_size(&self) -> i32 {
+        1
+    }
+
+    //In 1/ONE_G rows a tick, None for the gravity from the settings
+    fn get_gravity(&self, level: i32) -> Option<u32>;
+
+    fn get_timings(&self, _level: i32) -> Option<Timings> {
+        None
+    }
+
+    //Whether the game ends with a win
+    fn is_complete(&self, _level: i32, _statistics: &Statistics) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeType {
+    FreePlay,
+    Marathon,
+    Endless,
+    Master
+}
+
+impl ModeType {
+    pub fn get(&self) -> &'static dyn GameMode {
+        match self {
+            ModeType::FreePlay => &FreePlay,
+            ModeType::Marathon => &Marathon,
+            ModeType::Endless => &Endless,
+            ModeType::Master => &Master
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ModeType::FreePlay => ModeType::Marathon,
+            ModeType::Marathon => ModeType::Endless,
+            ModeType::Endless => ModeType::Master,
+            ModeType::Master => ModeType::FreePlay
+        }
+    }
+}
+
+//The guideline's speed curve, (0.8 - (level - 1) * 0.007) ^ (level - 1) seconds a row, it's past 20G by level 19 and stays there
+fn get_guideline_gravity(level: i32) -> u32 {
+    let level = level.clamp(1, 19);
+    let seconds = (0.8 - (level - 1) as f32 * 0.007).powi(level - 1);
+    ((ONE_G as f32 / (seconds * TICKS_PER_SECOND as f32)).round() as u32).min(ONE_G * 20)
+}
+
+//The guideline's half a second of lock delay at every level, line clears get quicker once the speed passes 1G
+fn get_guideline_timings(level: i32) -> Timings {
+    let line_clear_delay = if get_guideline_gravity(level) >= ONE_G { 12 } else { 20 };
+    Timings { are: 6, line_clear_delay, lock_delay: 30 }
+}
+
+pub struct FreePlay;
+
+impl GameMode for FreePlay {
+    fn name(&self) -> &'static str {
+        "FREE PLAY"
+    }
+
+    fn has_levels(&self) -> bool {
+        false
+    }
+
+    fn get_clear_level(&self, level: i32, _lines: i32, _statistics: &Statistics) -> i32 {
+        level
+    }
+
+    fn get_gravity(&self, _level: i32) -> Option<u32> {
+        None
+    }
+}
+
+pub struct Marathon;
+
+impl Marathon {
+    const LEVELS: i32 = 15;
+    const LINES_PER_LEVEL: i32 = 10;
+}
+
+impl GameMode for Marathon {
+    fn name(&self) -> &'static str {
+        "MARATHON"
+    }
+
+    fn has_levels(&self) -> bool {
+        true
+    }
+
+    fn get_clear_level(&self, _level: i32, _lines: i32, statistics: &Statistics) -> i32 {
+        (statistics.lines / Self::LINES_PER_LEVEL + 1).min(Self::LEVELS)
+    }
+
+    fn get_gravity(&self, level: i32) -> Option<u32> {
+        Some(get_guideline_gravity(level))
+    }
+
+    fn get_timings(&self, level: i32) -> Option<Timings> {
+        Some(get_guideline_timings(level))
+    }
+
+    fn is_complete(&self, _level: i32, statistics: &Statistics) -> bool {
+        statistics.lines >= Self::LEVELS * Self::LINES_PER_LEVEL
+    }
+}
+
+//Marathon without the end, the levels keep going and the speed stays at 20G once it gets there
+pub struct Endless;
+
+impl GameMode for Endless {
+    fn name(&self) -> &'static str {
+        "ENDLESS"
+    }
+
+    fn has_levels(&self) -> bool {
+        true
+    }
+
+    fn get_clear_level(&self, _level: i32, _lines: i32, statistics: &Statistics) -> i32 {
+        statistics.lines / Marathon::LINES_PER_LEVEL + 1
+    }
+
+    fn get_gravity(&self, level: i32) -> Option<u32> {
+        Some(get_guideline_gravity(level))
+    }
+
+    fn get_timings(&self, level: i32) -> Option<Timings> {
+        Some(get_guideline_timings(level))
+    }
+}
+
+//20G from the first piece, so pieces appear already resting on the stack and lock delay is all the time there is
+//The level goes up with every piece and every cleared line like in TGM, but pieces alone can't finish a section (x99)
+//Delays shrink every section of 100 levels and reaching level 999 wins
+pub struct Master;
+
+impl Master {
+    const SECTION_SIZE: i32 = 100;
+    const MAX_LEVEL: i32 = 999;
+    //ARE, line clear delay and lock delay of each section, the last one lasts until the end
+    const SECTION_TIMINGS: [(u32, u32, u32); 6] = [(16, 12, 30), (12, 6, 26), (12, 6, 22), (6, 6, 18), (5, 5, 15), (4, 4, 15)];
+}
+
+impl GameMode for Master {
+    fn name(&self) -> &'static str {
+        "MASTER"
+    }
+
+    fn has_levels(&self) -> bool {
+        true
+    }
+
+    fn get_start_level(&self) -> i32 {
+        0
+    }
+
+    fn get_spawn_level(&self, level: i32) -> i32 {
+        if level % Self::SECTION_SIZE == Self::SECTION_SIZE - 1 || level >= Self::MAX_LEVEL - 1 { level } else { level + 1 }
+    }
+
+    fn get_clear_level(&self, level: i32, lines: i32, _statistics: &Statistics) -> i32 {
+        (level + lines).min(Self::MAX_LEVEL)
+    }
+
+    fn get_section_size(&self) -> i32 {
+        Self::SECTION_SIZE
+    }
+
+    fn get_gravity(&self, _level: i32) -> Option<u32> {
+        Some(ONE_G * 20)
+    }
+
+    fn get_timings(&self, level: i32) -> Option<Timings> {
+        let section = ((level / Self::SECTION_SIZE) as usize).min(Self::SECTION_TIMINGS.len() - 1);
+        let (are, line_clear_delay, lock_delay) = Self::SECTION_TIMINGS[section];
+        Some(Timings { are, line_clear_delay, lock_delay })
+    }
+
+    fn is_complete(&self, level: i32, _statistics: &Statistics) -> bool {
+        level >= Self::MAX_LEVEL
+    }
+}
@@ -0,0 +1,62 @@
+use ggez::{glam::Vec2, graphics::{Canvas, Color}};
+
+use crate::{events::{GameEvent, Subscriber}, theme::Theme, util::{self, CELL_SIZE, SCREEN_SIZE}};
+
+//Seconds the text stays up, the same however long the line clear delay is, even without one
+const DURATION: f32 = 1.0;
+
+//The text shown over the board after lines clear
+pub struct ClearPopup {
+    text: String,
+    //Row the text is drawn above, the highest cleared one
+    i: i32,
+    perfect_clear: bool,
+    life: f32
+}
+
+impl ClearPopup {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            i: 0,
+            perfect_clear: false,
+            life: 0.0
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.life = (self.life - dt).max(0.0);
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, theme: &Theme) {
+        if self.life <= 0.0 { return; }
+
+        let opacity = self.life / DURATION;
+        let color = Color::new(theme.text.r * opacity, theme.text.g * opacity, theme.text.b * opacity, 1.0);
+        util::draw_text_centered_on(canvas, &self.text, Vec2::new(SCREEN_SIZE.x / 2.0, (self.i as f32 - 0.5) * CELL_SIZE), CELL_SIZE * 0.6, color);
+        if self.perfect_clear { util::draw_text_centered_on(canvas, "PERFECT CLEAR!", SCREEN_SIZE / 2.0, CELL_SIZE, color); }
+    }
+}
+
+impl Default for ClearPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Subscriber for ClearPopup {
+    fn handle(&mut self, event: &GameEvent) {
+        if let GameEvent::LinesCleared { rows, count, tspin, b2b, perfect_clear, score, .. } = event {
+            let name = match count {
+                1 => "single",
+                2 => "double",
+                3 => "triple",
+                _ => "tetris"
+            };
+            self.text = format!("{}{}{}! {} score", if *b2b { "b2b " } else { "" }, if *tspin { "t-spin " } else { "" }, name, score);
+            self.i = rows.iter().map(|row| row.0).min().unwrap_or(0);
+            self.perfect_clear = *perfect_clear;
+            self.life = DURATION;
+        }
+    }
+}
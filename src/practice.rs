@@ -0,0 +1,78 @@
+use crate::{board::Board, game::Statistics, teto::{Teto, TetoType}};
+
+//Everything needed to go back to a position, the current teto is back at its spawn
+#[derive(Clone)]
+pub struct Snapshot {
+    pub board: Board,
+    pub teto: Teto,
+    pub next_teto: Teto,
+    pub held_teto: Option<Teto>,
+    pub tetos: Vec<TetoType>,
+    pub queue: Vec<TetoType>,
+    pub can_hold: bool,
+    pub score: i32,
+    pub level: i32,
+    pub splits: Vec<u32>,
+    pub statistics: Statistics
+}
+
+//Free play for drilling setups, the board can be edited and positions saved and retried
+pub struct Practice {
+    pub gravity: bool,
+    pub editing: bool,
+    //What painting a cell puts there, None erases
+    pub brush: Option<TetoType>,
+    //Tetos that come before the bag, starting with the current one once the editor is closed
+    pub queue: Vec<TetoType>,
+    saved: Option<Snapshot>,
+    //Positions before each lock, the last one is undone first
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>
+}
+
+impl Practice {
+    pub fn new() -> Self {
+        Self {
+            gravity: true,
+            editing: false,
+            brush: Some(TetoType::Garbage),
+            queue: Vec::new(),
+            saved: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new()
+        }
+    }
+
+    pub fn save(&mut self, snapshot: Snapshot) {
+        self.saved = Some(snapshot);
+    }
+
+    //A new lock makes the undone placements unreachable
+    pub fn push_undo(&mut self, snapshot: Snapshot) {
+        self.undo_stack.push(snapshot);
+        self.redo_stack.clear();
+    }
+
+    //Takes the position to go back to and keeps current for redoing
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.undo_stack.pop()?;
+        self.redo_stack.push(current);
+        Some(snapshot)
+    }
+
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo_stack.pop()?;
+        self.undo_stack.push(current);
+        Some(snapshot)
+    }
+
+    pub fn get_saved(&self) -> Option<&Snapshot> {
+        self.saved.as_ref()
+    }
+}
+
+impl Default for Practice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,137 @@
+use crate::teto::TetoType;
+
+pub trait RotationSystem {
+    fn name(&self) -> &'static str;
+
+    //Directory inside ./res holding this system's rotation states
+    fn res_dir(&self) -> &'static str;
+
+    //Offsets (i, j) tried in order after the unkicked rotation fails
+    //blocked holds the cells of the new rotation (relative to the teto) that collided without a kick
+    fn get_wall_kicks(&self, teto_type: &TetoType, prev_rot: usize, dir: i32, blocked: &[(i32, i32)]) -> Vec<(i32, i32)>;
+
+    fn can_rotate_180(&self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationSystemType {
+    Srs,
+    Ars,
+    Nes
+}
+
+impl RotationSystemType {
+    pub fn get(&self) -> &'static dyn RotationSystem {
+        match self {
+            RotationSystemType::Srs => &Srs,
+            RotationSystemType::Ars => &Ars,
+            RotationSystemType::Nes => &Nes
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            RotationSystemType::Srs => RotationSystemType::Ars,
+            RotationSystemType::Ars => RotationSystemType::Nes,
+            RotationSystemType::Nes => RotationSystemType::Srs
+        }
+    }
+}
+
+//Kick tables are written as (x, y) with y pointing up like on the wiki, this converts them to (i, j)
+fn from_xy(kicks: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    kicks.iter().map(|kick| (-kick.1, kick.0)).collect()
+}
+
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn name(&self) -> &'static str {
+        "SRS"
+    }
+
+    fn res_dir(&self) -> &'static str {
+        "srs"
+    }
+
+    fn get_wall_kicks(&self, teto_type: &TetoType, prev_rot: usize, dir: i32, _blocked: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        if matches!(teto_type, TetoType::O) { return [].into(); }
+
+        //SRS+ 180 kicks
+        if dir.abs() == 2 {
+            return from_xy(match prev_rot {
+                0 => &[(0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+                1 => &[(1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+                2 => &[(0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+                _ => &[(-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)]
+            });
+        }
+
+        let new_rot = (prev_rot as i32 + dir).rem_euclid(4);
+        if matches!(teto_type, TetoType::I) {
+            return from_xy(match (prev_rot, new_rot) {
+                (0, 1) | (3, 2) => &[(-2, 0), (1, 0), (-2, -1), (1, 2)],
+                (1, 0) | (2, 3) => &[(2, 0), (-1, 0), (2, 1), (-1, -2)],
+                (1, 2) | (0, 3) => &[(-1, 0), (2, 0), (-1, 2), (2, -1)],
+                _ => &[(1, 0), (-2, 0), (1, -2), (-2, 1)]
+            });
+        }
+
+        from_xy(match (prev_rot, new_rot) {
+            (0, 1) | (2, 1) => &[(-1, 0), (-1, 1), (0, -2), (-1, -2)],
+            (1, 0) | (1, 2) => &[(1, 0), (1, -1), (0, 2), (1, 2)],
+            (2, 3) | (0, 3) => &[(1, 0), (1, 1), (0, -2), (1, -2)],
+            _ => &[(-1, 0), (-1, -1), (0, 2), (-1, 2)]
+        })
+    }
+
+    fn can_rotate_180(&self) -> bool {
+        true
+    }
+}
+
+//TGM-style: one step right then one step left, I never kicks and neither do J, L and T when the center column is in the way
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn name(&self) -> &'static str {
+        "ARS"
+    }
+
+    fn res_dir(&self) -> &'static str {
+        "ars"
+    }
+
+    fn get_wall_kicks(&self, teto_type: &TetoType, _prev_rot: usize, _dir: i32, blocked: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        match teto_type {
+            TetoType::I | TetoType::O => [].into(),
+            TetoType::J | TetoType::L | TetoType::T if blocked.iter().min().is_some_and(|pos| pos.1 == 1) => [].into(),
+            _ => [(0, 1), (0, -1)].into()
+        }
+    }
+
+    fn can_rotate_180(&self) -> bool {
+        false
+    }
+}
+
+//Classic NES: rotations either fit where they are or don't happen
+pub struct Nes;
+
+impl RotationSystem for Nes {
+    fn name(&self) -> &'static str {
+        "NES"
+    }
+
+    fn res_dir(&self) -> &'static str {
+        "nes"
+    }
+
+    fn get_wall_kicks(&self, _teto_type: &TetoType, _prev_rot: usize, _dir: i32, _blocked: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        [].into()
+    }
+
+    fn can_rotate_180(&self) -> bool {
+        false
+    }
+}
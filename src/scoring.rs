@@ -0,0 +1,154 @@
+//Everything a scoring rule gets to know about a lock that cleared lines (or was a t-spin)
+pub struct Clear {
+    pub lines: i32,
+    pub tspin: bool,
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+    //Clears in a row including this one
+    pub combo: i32,
+    //The game's level when the teto locked, modes without levels stay at 1
+    pub level: i32,
+    //Which level that is counting the mode's start level as 1, master starts at level 0
+    pub level_count: i32,
+    //Cells the locking teto was soft dropped
+    pub soft_dropped: i32
+}
+
+pub trait ScoringRule {
+    fn name(&self) -> &'static str;
+
+    fn get_clear_score(&self, clear: &Clear) -> i32;
+
+    fn get_drop_score(&self, soft_dropped: i32, hard_dropped: i32) -> i32;
+
+    //Rules that grade the player instead of only counting points
+    fn get_grade(&self, _score: i32) -> Option<&'static str> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoringType {
+    Guideline,
+    Nes,
+    Tgm
+}
+
+impl ScoringType {
+    pub fn get(&self) -> &'static dyn ScoringRule {
+        match self {
+            ScoringType::Guideline => &Guideline,
+            ScoringType::Nes => &Nes,
+            ScoringType::Tgm => &Tgm
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ScoringType::Guideline => ScoringType::Nes,
+            ScoringType::Nes => ScoringType::Tgm,
+            ScoringType::Tgm => ScoringType::Guideline
+        }
+    }
+}
+
+pub struct Guideline;
+
+impl ScoringRule for Guideline {
+    fn name(&self) -> &'static str {
+        "GUIDELINE"
+    }
+
+    //Everything is multiplied by the level counted from 1
+    fn get_clear_score(&self, clear: &Clear) -> i32 {
+        let level = clear.level_count;
+        let lines = if clear.tspin {
+            match clear.lines {
+                0 => 400,
+                1 => 800,
+                2 => 1200,
+                _ => 1600
+            }
+        } else {
+            match clear.lines {
+                0 => 0,
+                1 => 100,
+                2 => 300,
+                3 => 500,
+                _ => 800
+            }
+        };
+        let lines = if clear.back_to_back { lines * 3 / 2 } else { lines };
+
+        let combo = if clear.lines > 0 { (clear.combo - 1) * 50 } else { 0 };
+        let perfect_clear = if !clear.perfect_clear { 0 } else {
+            match clear.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ => if clear.back_to_back { 3200 } else { 2000 }
+            }
+        };
+
+        (lines + combo + perfect_clear) * level
+    }
+
+    fn get_drop_score(&self, soft_dropped: i32, hard_dropped: i32) -> i32 {
+        soft_dropped + hard_dropped * 2
+    }
+}
+
+pub struct Nes;
+
+impl ScoringRule for Nes {
+    fn name(&self) -> &'static str {
+        "NES"
+    }
+
+    //The NES counts levels from 0, so the game's first level pays like its level 0
+    fn get_clear_score(&self, clear: &Clear) -> i32 {
+        clear.level_count * match clear.lines {
+            0 => 0,
+            1 => 40,
+            2 => 100,
+            3 => 300,
+            _ => 1200
+        }
+    }
+
+    //The NES has no hard drop, only holding down scores
+    fn get_drop_score(&self, soft_dropped: i32, _hard_dropped: i32) -> i32 {
+        soft_dropped
+    }
+}
+
+pub struct Tgm;
+
+impl Tgm {
+    const GRADES: [(i32, &'static str); 18] = [
+        (0, "9"), (400, "8"), (800, "7"), (1400, "6"), (2000, "5"), (3500, "4"), (5500, "3"), (8000, "2"), (12000, "1"),
+        (16000, "S1"), (22000, "S2"), (30000, "S3"), (40000, "S4"), (52000, "S5"), (66000, "S6"), (82000, "S7"), (100000, "S8"), (120000, "S9")
+    ];
+}
+
+impl ScoringRule for Tgm {
+    fn name(&self) -> &'static str {
+        "TGM"
+    }
+
+    //Soft dropping and perfect clears (bravo) multiply the score
+    fn get_clear_score(&self, clear: &Clear) -> i32 {
+        if clear.lines == 0 { return 0; }
+
+        let bravo = if clear.perfect_clear { 4 } else { 1 };
+        ((clear.level + clear.lines + 3) / 4 + clear.soft_dropped) * clear.lines * (clear.combo * 2 - 1) * bravo
+    }
+
+    fn get_drop_score(&self, _soft_dropped: i32, _hard_dropped: i32) -> i32 {
+        0
+    }
+
+    fn get_grade(&self, score: i32) -> Option<&'static str> {
+        Self::GRADES.iter().rev().find(|grade| score >= grade.0).map(|grade| grade.1)
+    }
+}
@@ -0,0 +1,226 @@
+use std::{fmt::Debug, fs, path::PathBuf};
+
+use crate::{assets, error::{Error, Result}, input::{Bindings, ACTIONS}, mode::ModeType, rotation::RotationSystemType, scoring::ScoringType};
+
+#[derive(Debug, Clone, Copy)]
+pub struct EffectToggles {
+    pub lock_flash: bool,
+    pub particles: bool,
+    pub screen_shake: bool,
+    pub hard_drop_trails: bool,
+    pub row_collapse: bool,
+    pub level_flash: bool
+}
+
+impl EffectToggles {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            lock_flash: enabled,
+            particles: enabled,
+            screen_shake: enabled,
+            hard_drop_trails: enabled,
+            row_collapse: enabled,
+            level_flash: enabled
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.lock_flash || self.particles || self.screen_shake || self.hard_drop_trails || self.row_collapse || self.level_flash
+    }
+}
+
+//The values a number setting is kept between and how much the settings menu changes it by
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+    pub step: f32
+}
+
+impl Range {
+    pub fn clamp(&self, value: f32) -> f32 {
+        if value.is_nan() { self.min } else { value.clamp(self.min, self.max) }
+    }
+
+    //Goes around to the other end past min or max, so clicking through all the values gets back to the start
+    pub fn step(&self, value: f32, dir: i32) -> f32 {
+        let value = ((value + self.step * dir as f32) / self.step).round() * self.step;
+        if value > self.max + self.step / 2.0 { self.min } else if value < self.min - self.step / 2.0 { self.max } else { self.clamp(value) }
+    }
+}
+
+pub const ARE_RANGE: Range = Range { min: 0.0, max: 1.0, step: 0.05 };
+pub const LINE_CLEAR_DELAY_RANGE: Range = Range { min: 0.0, max: 1.0, step: 0.05 };
+pub const DAS_RANGE: Range = Range { min: 0.0, max: 0.5, step: 0.01 };
+pub const ARR_RANGE: Range = Range { min: 0.0, max: 0.2, step: 0.01 };
+pub const FALL_DURATION_RANGE: Range = Range { min: 0.05, max: 2.0, step: 0.05 };
+pub const VOLUME_RANGE: Range = Range { min: 0.0, max: 1.0, step: 0.1 };
+
+const CONFIG_FILE: &str = "settings.cfg";
+
+pub struct Settings {
+    pub mode: ModeType,
+    pub rotation_system: RotationSystemType,
+    pub scoring: ScoringType,
+    //Seconds between a teto locking and the next one spawning (ARE)
+    pub are: f32,
+    //Extra seconds before spawning when the lock cleared lines, also the length of the clear animation
+    pub line_clear_delay: f32,
+    //Seconds a move key is held before it repeats (DAS) and between repeats (ARR)
+    pub das: f32,
+    pub arr: f32,
+    //Seconds a teto takes to fall one row by itself, in modes without levels
+    pub fall_duration: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub muted: bool,
+    pub theme: String,
+    pub fullscreen: bool,
+    pub effects: EffectToggles,
+    //Buttons on screen for playing with the mouse or a touch screen
+    pub touch_controls: bool,
+    pub bindings: Bindings,
+    //Fumen string of the board every game starts from
+    pub fumen: Option<String>
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            mode: ModeType::FreePlay,
+            rotation_system: RotationSystemType::Srs,
+            scoring: ScoringType::Guideline,
+            are: 0.1,
+            line_clear_delay: 0.25,
+            das: 0.2,
+            arr: 0.1,
+            fall_duration: 0.5,
+            sfx_volume: 0.8,
+            music_volume: 0.5,
+            muted: false,
+            theme: "default".to_owned(),
+            fullscreen: false,
+            effects: EffectToggles::new(true),
+            touch_controls: false,
+            bindings: Bindings::new(),
+            fumen: None
+        }
+    }
+
+    //The config file in the user's config directory, None if the system doesn't have one
+    pub fn get_config_path() -> Option<PathBuf> {
+        assets::get_config_path(CONFIG_FILE)
+    }
+
+    //Settings from the config file, anything missing or invalid in it keeps its default
+    pub fn load() -> Self {
+        let mut settings = Self::new();
+        let Some(path) = Self::get_config_path() else { return settings; };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            log::info!("No settings at {}, using the defaults", path.display());
+            return settings;
+        };
+        log::info!("Loaded settings from {}", path.display());
+
+        for line in contents.lines() {
+            if line.starts_with("--") { continue; }
+            let Some((key, value)) = line.split_once('=') else { continue; };
+            settings.set(key.trim(), value.trim());
+        }
+
+        settings.validate();
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let number = value.parse::<f32>().ok();
+        let flag = value.parse::<bool>().ok();
+
+        match key {
+            "mode" => self.mode = parse_cycle(self.mode, |mode| mode.next(), value).unwrap_or(self.mode),
+            "rotation_system" => self.rotation_system = parse_cycle(self.rotation_system, |rotation_system| rotation_system.next(), value).unwrap_or(self.rotation_system),
+            "scoring" => self.scoring = parse_cycle(self.scoring, |scoring| scoring.next(), value).unwrap_or(self.scoring),
+            "are" => self.are = number.unwrap_or(self.are),
+            "line_clear_delay" => self.line_clear_delay = number.unwrap_or(self.line_clear_delay),
+            "das" => self.das = number.unwrap_or(self.das),
+            "arr" => self.arr = number.unwrap_or(self.arr),
+            "fall_duration" => self.fall_duration = number.unwrap_or(self.fall_duration),
+            "sfx_volume" => self.sfx_volume = number.unwrap_or(self.sfx_volume),
+            "music_volume" => self.music_volume = number.unwrap_or(self.music_volume),
+            "muted" => self.muted = flag.unwrap_or(self.muted),
+            "theme" => self.theme = value.to_owned(),
+            "fullscreen" => self.fullscreen = flag.unwrap_or(self.fullscreen),
+            "effects.lock_flash" => self.effects.lock_flash = flag.unwrap_or(self.effects.lock_flash),
+            "effects.particles" => self.effects.particles = flag.unwrap_or(self.effects.particles),
+            "effects.screen_shake" => self.effects.screen_shake = flag.unwrap_or(self.effects.screen_shake),
+            "effects.hard_drop_trails" => self.effects.hard_drop_trails = flag.unwrap_or(self.effects.hard_drop_trails),
+            "effects.row_collapse" => self.effects.row_collapse = flag.unwrap_or(self.effects.row_collapse),
+            "effects.level_flash" => self.effects.level_flash = flag.unwrap_or(self.effects.level_flash),
+            "touch_controls" => self.touch_controls = flag.unwrap_or(self.touch_controls),
+            _ => if let Some(action) = key.strip_prefix("bind.").and_then(|name| ACTIONS.into_iter().find(|action| format!("{:?}", action) == name)) {
+                let keys: Vec<&str> = value.split(',').map(|key| key.trim()).filter(|key| !key.is_empty()).collect();
+                self.bindings.set_keys(action, &keys);
+            }
+        }
+    }
+
+    //Puts every number back in its range
+    pub fn validate(&mut self) {
+        self.are = ARE_RANGE.clamp(self.are);
+        self.line_clear_delay = LINE_CLEAR_DELAY_RANGE.clamp(self.line_clear_delay);
+        self.das = DAS_RANGE.clamp(self.das);
+        self.arr = ARR_RANGE.clamp(self.arr);
+        self.fall_duration = FALL_DURATION_RANGE.clamp(self.fall_duration);
+        self.sfx_volume = VOLUME_RANGE.clamp(self.sfx_volume);
+        self.music_volume = VOLUME_RANGE.clamp(self.music_volume);
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_config_path().ok_or(Error::NoDirectory("config"))?;
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?; }
+
+        let mut lines = vec![
+            "--Tetris settings, written by the game".to_owned(),
+            format!("mode = {:?}", self.mode),
+            format!("rotation_system = {:?}", self.rotation_system),
+            format!("scoring = {:?}", self.scoring),
+            format!("are = {}", self.are),
+            format!("line_clear_delay = {}", self.line_clear_delay),
+            format!("das = {}", self.das),
+            format!("arr = {}", self.arr),
+            format!("fall_duration = {}", self.fall_duration),
+            format!("sfx_volume = {}", self.sfx_volume),
+            format!("music_volume = {}", self.music_volume),
+            format!("muted = {}", self.muted),
+            format!("theme = {}", self.theme),
+            format!("fullscreen = {}", self.fullscreen),
+            format!("effects.lock_flash = {}", self.effects.lock_flash),
+            format!("effects.particles = {}", self.effects.particles),
+            format!("effects.screen_shake = {}", self.effects.screen_shake),
+            format!("effects.hard_drop_trails = {}", self.effects.hard_drop_trails),
+            format!("effects.row_collapse = {}", self.effects.row_collapse),
+            format!("effects.level_flash = {}", self.effects.level_flash),
+            format!("touch_controls = {}", self.touch_controls)
+        ];
+        for action in ACTIONS { lines.push(format!("bind.{:?} = {}", action, self.bindings.get_keys(action).join(", "))); }
+
+        fs::write(&path, lines.join("\n") + "\n").map_err(|err| Error::io(&path, err))?;
+        log::info!("Saved settings to {}", path.display());
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//Finds the value of a setting that cycles with next() by its debug name
+fn parse_cycle<T: Copy + PartialEq + Debug>(first: T, next: impl Fn(T) -> T, name: &str) -> Option<T> {
+    let mut value = first;
+    loop {
+        if format!("{:?}", value).eq_ignore_ascii_case(name) { return Some(value); }
+        value = next(value);
+        if value == first { return None; }
+    }
+}
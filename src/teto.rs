@@ -1,13 +1,23 @@
-use std::{collections::HashMap, fs};
+#[cfg(feature = "gui")]
+use ggez::graphics::Canvas;
 
-use ggez::{graphics::{Canvas, Color}, input::keyboard::KeyCode, Context};
+use crate::{assets, board::{Board, GRID_SIZE}, error::{Error, Result}, das::Das, events::{EventBus, GameEvent}, input::{Action, Input}, rotation::RotationSystemType};
+#[cfg(feature = "gui")]
+use crate::{theme::Theme, util::CELL_SIZE};
 
-use crate::{board::Board, util::{self, CELL_SIZE, GRID_SIZE}};
-
-type TetoRot = Vec<(i32, i32)>;
+pub type TetoRot = Vec<(i32, i32)>;
 type TetoRots = Vec<TetoRot>;
 
-#[derive(Debug, Clone)]
+const ROTATE_ACTIONS: [(Action, i32); 3] = [(Action::RotateCcw, -1), (Action::RotateCw, 1), (Action::Rotate180, 2)];
+pub const MOVE_ACTIONS: [(Action, i32); 2] = [(Action::MoveLeft, -1), (Action::MoveRight, 1)];
+//1G is a row a tick, gravity is kept in 1/ONE_G rows a tick so it can be slower than a row a tick or many rows a tick
+pub const ONE_G: u32 = 65536;
+//Actions that repeat while held, with DAS and ARR
+pub const REPEAT_ACTIONS: [Action; 3] = [Action::MoveLeft, Action::MoveRight, Action::SoftDrop];
+//Taps that still move a teto when they come during ARE, a soft drop would lock a 20G teto as it spawns
+pub const BUFFERED_ACTIONS: [Action; 2] = [Action::MoveLeft, Action::MoveRight];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TetoType {
     I,
     O,
@@ -15,53 +25,85 @@ pub enum TetoType {
     S,
     Z,
     J,
-    L
+    L,
+    //Gray cells from imported setups, never spawned as a teto
+    Garbage
+}
+
+impl TetoType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Teto::get_teto_bag().into_iter().chain([TetoType::Garbage]).find(|teto_type| format!("{:?}", teto_type) == name)
+    }
 }
 
+//How far cells has to be moved to line up with other, if they are the same shape
+pub fn alignment(cells: &[(i32, i32)], other: &[(i32, i32)]) -> Option<(i32, i32)> {
+    let mut cells = cells.to_vec();
+    let mut other = other.to_vec();
+    cells.sort();
+    other.sort();
+
+    let offset = (other.first()?.0 - cells.first()?.0, other[0].1 - cells[0].1);
+    if cells.len() != other.len() || cells.iter().zip(other.iter()).any(|(cell, other)| (cell.0 + offset.0, cell.1 + offset.1) != *other) { return None; }
+    Some(offset)
+}
+
+#[derive(Clone)]
 pub struct Teto {
     t: TetoType,
+    rotation_system: RotationSystemType,
     rots: TetoRots,
     rot: usize,
     i: i32,
     j: i32,
-    fall_duration: f32,
-    prev_fall_time: f32,
+    //Gravity in 1/ONE_G rows a tick and how far the teto has fallen towards the next row
+    fall_speed: u32,
+    fall_progress: u32,
+    //Ticks the teto can rest on something before it locks and ticks it has been resting, 0 locks it as soon as it lands
+    lock_delay: u32,
+    lock_frames: u32,
     dead: bool,
-    keys_last_time_pressed: HashMap<KeyCode, f32>
+    last_move_rotated: bool,
+    gravity: bool
 }
 
 impl Teto {
-    pub fn new(teto_type: TetoType) -> Self {
-        let rots = Self::parse_teto(&teto_type);
+    pub fn new(teto_type: TetoType, rotation_system: RotationSystemType) -> Result<Self> {
+        let rots = Self::parse_teto(&teto_type, rotation_system)?;
 
         let mut teto = Self {
             t: teto_type,
-            rots: rots,
+            rotation_system,
+            rots,
             rot: 0,
             i: 0,
             j: 0,
-            fall_duration: 0.5,
-            prev_fall_time: 0.0,
+            fall_speed: ONE_G / 30,
+            fall_progress: 0,
+            lock_delay: 0,
+            lock_frames: 0,
             dead: false,
-            keys_last_time_pressed: HashMap::new()
+            last_move_rotated: false,
+            gravity: true
         };
         teto.reset();
 
-        teto
+        Ok(teto)
     }
     
     pub fn get_teto_bag() -> Vec<TetoType> {
         Vec::from_iter([TetoType::I, TetoType::O, TetoType::T, TetoType::S, TetoType::Z, TetoType::J, TetoType::L])
     }
 
-    fn parse_teto(teto_type: &TetoType) -> TetoRots {
-        let filepath = format!("./res/{:?}", teto_type);
+    fn parse_teto(teto_type: &TetoType, rotation_system: RotationSystemType) -> Result<TetoRots> {
+        let path = format!("res/{}/{:?}", rotation_system.get().res_dir(), teto_type);
+        let invalid = |reason: &str| Error::InvalidAsset { path: path.clone(), reason: reason.to_owned() };
         let mut all = false;
 
         let mut i = 0;
         let mut rots: TetoRots = Vec::new();
 
-        for line in fs::read_to_string(filepath).expect("Teto file failed :(").lines() {
+        for line in assets::get_teto_shape(rotation_system.get().res_dir(), *teto_type).ok_or(invalid("there is no shape for this teto"))?.lines() {
             if line.starts_with("--") {
                 if line == "--all" { all = true; }
                 rots.push(Vec::new());
@@ -70,43 +112,65 @@ impl Teto {
             }
             for c in line.bytes().enumerate() {
                 if c.1 == b' ' { continue; }
-                rots.last_mut().expect("Invalid Teto :(").push((i, c.0 as i32));
+                rots.last_mut().ok_or(invalid("cells come before the first -- line"))?.push((i, c.0 as i32));
             }
             i += 1;
         }
         
-        if all {
+        if all && !rots.is_empty() {
             rots.push(rots[0].clone());
             rots.push(rots[0].clone());
             rots.push(rots[0].clone());
         }
-        rots
+        if rots.len() != 4 { return Err(invalid(&format!("it has {} rotations instead of 4", rots.len()))); }
+        if rots.iter().any(|rot| rot.is_empty()) { return Err(invalid("a rotation has no cells")); }
+        Ok(rots)
+    }
+
+    //Every teto of every rotation system is parsed once so a broken shape is an error here instead of a crash mid game
+    pub fn check_shapes() -> Result<()> {
+        for rotation_system in [RotationSystemType::Srs, RotationSystemType::Ars, RotationSystemType::Nes] {
+            for teto_type in Self::get_teto_bag() { Self::parse_teto(&teto_type, rotation_system)?; }
+        }
+        Ok(())
     }
 
     pub fn reset(&mut self) {
-        let width = self.rots[0].iter().map(|pos| pos.1).max().expect("Empty Teto?! :O") - self.rots[0].iter().map(|pos| pos.1).min().expect("Empty Teto?! :O") + 1;
-        self.i = -self.rots[0].iter().map(|pos| pos.0).max().expect("Empty Teto?! :O") - 1;
-        self.j = (GRID_SIZE.j - width) / 2 - 1;
+        let min_j = self.rots[0].iter().map(|pos| pos.1).min().unwrap_or(0);
+        let width = self.rots[0].iter().map(|pos| pos.1).max().unwrap_or(0) - min_j + 1;
+        self.i = -self.rots[0].iter().map(|pos| pos.0).max().unwrap_or(0) - 1;
+        self.j = (GRID_SIZE.j - width) / 2 - 1 - min_j;
         self.rot = 0;
+        self.fall_progress = 0;
+        self.lock_frames = 0;
+        self.dead = false;
+        self.last_move_rotated = false;
     }
 
-    fn is_key_repeat(&mut self, ctx: &Context, key: KeyCode) -> bool {
-        if !self.keys_last_time_pressed.contains_key(&key) { self.keys_last_time_pressed.insert(key, util::get_time(ctx) + 0.2); }
-        if ctx.keyboard.is_key_just_pressed(key) {
-            self.keys_last_time_pressed.insert(key, util::get_time(ctx) + 0.2);
-            return true;
-        }
-        else if ctx.keyboard.is_key_pressed(key) && util::get_time(ctx) - self.keys_last_time_pressed[&key] > 0.1 {
-            self.keys_last_time_pressed.insert(key, util::get_time(ctx));
-            return true;
-        }
-        false
+    //Without gravity the teto only falls when dropped
+    pub fn set_gravity(&mut self, gravity: bool) {
+        self.gravity = gravity;
+    }
+
+    pub fn set_fall_speed(&mut self, fall_speed: u32) {
+        self.fall_speed = fall_speed;
+    }
+
+    pub fn set_lock_delay(&mut self, lock_delay: u32) {
+        self.lock_delay = lock_delay;
     }
 
-    pub fn update(&mut self, ctx: &Context, board: &Board) {
-        self.rotate(ctx, board);
-        self.do_move(ctx, board);
-        self.try_fall(ctx, board);
+    //One tick
+    pub fn update(&mut self, input: &Input, board: &Board, das: &Das, events: &mut EventBus) {
+        self.rotate(input, board, events);
+        self.do_move(board, das, events);
+        self.try_fall(input, board, das, events);
+
+        //Only falling a row resets the lock delay, moving and rotating on the ground don't
+        if self.lock_delay > 0 && !self.dead && self.is_grounded(board) {
+            self.lock_frames += 1;
+            if self.lock_frames >= self.lock_delay { self.dead = true; }
+        }
     }
 
     pub fn is_dead(&self) -> bool {
@@ -117,38 +181,98 @@ impl Teto {
         self.rots[self.rot].iter().map(|pos| (pos.0 + self.i, pos.1 + self.j)).collect()
     }
 
-    fn rotate(&mut self, ctx: &Context, board: &Board) {
-        self.rotate_dir(ctx, board, KeyCode::Left, -1);
-        self.rotate_dir(ctx, board, KeyCode::Right, 1);
+    pub fn get_rot_state(&self) -> usize {
+        self.rot
     }
 
-    fn rotate_dir(&mut self, ctx: &Context, board: &Board, key: KeyCode, dir: i32) {
-        if ctx.keyboard.is_key_just_pressed(key) {
-            self.reset_fall(ctx);
-            self.try_wall_kick(board, dir);
+    pub fn get_pos(&self) -> (i32, i32) {
+        (self.i, self.j)
+    }
+
+    pub fn get_fall_speed(&self) -> u32 {
+        self.fall_speed
+    }
+
+    //In 1/ONE_G rows
+    pub fn get_fall_progress(&self) -> u32 {
+        self.fall_progress
+    }
+
+    pub fn get_lock_delay(&self) -> u32 {
+        self.lock_delay
+    }
+
+    pub fn get_lock_frames(&self) -> u32 {
+        self.lock_frames
+    }
+
+    //Puts the teto exactly over cells, preferring rotation state rot since rotation systems don't all agree on which state has which shape
+    pub fn place(&mut self, rot: usize, cells: &[(i32, i32)]) -> bool {
+        for rot in [rot].into_iter().chain(0..self.rots.len()) {
+            if let Some(offset) = alignment(&self.rots[rot % self.rots.len()], cells) {
+                self.rot = rot % self.rots.len();
+                self.i = offset.0;
+                self.j = offset.1;
+                return true;
+            }
         }
+        false
     }
 
-    fn try_wall_kick(&mut self, board: &Board, dir: i32) {
-        let prev_rot = self.rot;
-        self.rot = ((self.rot + self.rots.len()) as i32 + dir) as usize % self.rots.len();
+    pub fn get_ghost_rot(&self, board: &Board) -> TetoRot {
+        let ghost_i_offset = self.get_ghost_i_offset(board);
+        self.get_rot().iter().map(|pos| (pos.0 + ghost_i_offset, pos.1)).collect()
+    }
 
-        if self.try_rot(board, 0, 0) { return; }
+    //The current rotation moved so that its top left corner is at (0, 0), for drawing previews
+    pub fn get_preview(&self) -> TetoRot {
+        let min_i = self.rots[self.rot].iter().map(|pos| pos.0).min().unwrap();
+        let min_j = self.rots[self.rot].iter().map(|pos| pos.1).min().unwrap();
+        self.rots[self.rot].iter().map(|pos| (pos.0 - min_i, pos.1 - min_j)).collect()
+    }
 
-        let wall_kicks = self.get_wall_kicks();
-        for wall_kick in wall_kicks {
-            if self.try_rot(board, wall_kick.0, wall_kick.1) { return; }
+    fn rotate(&mut self, input: &Input, board: &Board, events: &mut EventBus) {
+        for (action, dir) in ROTATE_ACTIONS { self.rotate_dir(input, board, events, action, dir); }
+    }
+
+    fn rotate_dir(&mut self, input: &Input, board: &Board, events: &mut EventBus, action: Action, dir: i32) {
+        if !self.can_rotate(dir) { return; }
+        if input.is_just_pressed(action) {
+            self.reset_fall();
+            if self.try_wall_kick(board, dir) { events.emit(GameEvent::Rotated); }
         }
+    }
 
-        self.rot = prev_rot;
+    //IRS: a rotate key that is already held down when the teto spawns rotates it right away
+    //One pressed on the spawn tick itself is left to the normal rotation that tick, or it would rotate twice
+    pub fn initial_rotate(&mut self, input: &Input, board: &Board) {
+        for (action, dir) in ROTATE_ACTIONS {
+            if self.can_rotate(dir) && input.is_pressed(action) && !input.is_just_pressed(action) {
+                self.try_wall_kick(board, dir);
+                return;
+            }
+        }
+    }
+
+    fn can_rotate(&self, dir: i32) -> bool {
+        dir.abs() != 2 || self.rotation_system.get().can_rotate_180()
     }
 
-    fn get_wall_kicks(&mut self) -> Vec<(i32, i32)> {
-        match self.t {
-            TetoType::I => [(-2, 0), (1, 0), (-2, 1), (1, -2)].into(),
-            TetoType::O => [].into(),
-            _ => [(-1, 0), (-1, -1), (0, 2), (-1, 2)].into()
+    fn try_wall_kick(&mut self, board: &Board, dir: i32) -> bool {
+        let prev_rot = self.rot;
+        self.rot = ((self.rot + self.rots.len()) as i32 + dir) as usize % self.rots.len();
+
+        let blocked: Vec<(i32, i32)> = self.rots[self.rot].iter().filter(|pos| self.collides_cell(board, pos.0, pos.1)).copied().collect();
+        let wall_kicks = if blocked.is_empty() { Vec::new() } else { self.rotation_system.get().get_wall_kicks(&self.t, prev_rot, dir, &blocked) };
+        for wall_kick in [(0, 0)].into_iter().chain(wall_kicks) {
+            if self.try_rot(board, wall_kick.0, wall_kick.1) {
+                self.last_move_rotated = true;
+                return true;
+            }
         }
+
+        self.rot = prev_rot;
+        false
     }
 
     fn try_rot(&mut self, board: &Board, i_offset: i32, j_offset: i32) -> bool {
@@ -162,62 +286,80 @@ impl Teto {
         true
     }
 
-    fn do_move(&mut self, ctx: &Context, board: &Board) {
-        self.move_dir(ctx, board, KeyCode::A, -1);
-        self.move_dir(ctx, board, KeyCode::D, 1);
-    }
+    fn do_move(&mut self, board: &Board, das: &Das, events: &mut EventBus) {
+        for (action, dir) in MOVE_ACTIONS {
+            if !das.is_repeat(action) { continue; }
 
-    fn move_dir(&mut self, ctx: &Context, board: &Board, key: KeyCode, dir: i32) {
-        if self.is_key_repeat(ctx, key) {
-            self.reset_fall(ctx);
+            self.reset_fall();
             self.j += dir;
             if self.collides(board) { self.j -= dir; }
+            else {
+                self.last_move_rotated = false;
+                events.emit(GameEvent::Moved);
+            }
         }
     }
 
-    fn try_fall(&mut self, ctx: &Context, board: &Board) {
-        if ctx.keyboard.is_key_just_pressed(KeyCode::Space) {
-            while !self.dead { self.fall(ctx, board); }
-        } else if self.is_key_repeat(ctx, KeyCode::S) {
-            self.reset_fall(ctx);
-            self.fall(ctx, board);
-        } else if util::get_time(ctx) - self.prev_fall_time > self.fall_duration { self.fall(ctx, board); }
+    //Soft dropping a teto that is already on the ground locks it
+    fn try_fall(&mut self, input: &Input, board: &Board, das: &Das, events: &mut EventBus) {
+        if input.is_just_pressed(Action::HardDrop) {
+            let start_i = self.i;
+            while self.fall(board) {}
+            self.dead = true;
+            events.emit(GameEvent::HardDropped { cells: self.get_rot(), distance: self.i - start_i, teto_type: self.t });
+            return;
+        }
+
+        if das.is_repeat(Action::SoftDrop) {
+            if self.fall(board) { events.emit(GameEvent::SoftDropped); }
+            else { self.dead = true; }
+        }
+        if self.gravity && !self.dead { self.apply_gravity(board); }
     }
 
-    fn fall(&mut self, ctx: &Context, board: &Board) {
-        self.reset_fall(ctx);
-        self.i += 1;
-        if self.collides(board) { self.die(); }
+    //At 20G a new teto lands before it can move or rotate, so it can't be shifted over the stack on the tick it spawns
+    pub fn spawn_fall(&mut self, board: &Board) {
+        if self.gravity && self.fall_speed >= ONE_G * 20 { while self.fall(board) {} }
     }
 
-    pub fn draw_centered_at(&self, canvas: &mut Canvas, x: f32, y: f32) {
-        let starting_i_offset = self.rots[self.rot].iter().map(|pos| pos.0).min().unwrap() as f32;
-        let width = (self.rots[self.rot].iter().map(|pos| pos.1).max().unwrap() - self.rots[self.rot].iter().map(|pos| pos.1).min().unwrap() + 1) as f32;
-        for pos in self.rots[self.rot].iter() {
-            util::draw_cell(canvas, x + (pos.1 as f32 - width / 2.0) * CELL_SIZE, y + (pos.0 as f32 - starting_i_offset / 2.0) * CELL_SIZE, self.color());
+    fn apply_gravity(&mut self, board: &Board) {
+        let progress = self.fall_progress + self.fall_speed;
+        for _ in 0..progress / ONE_G {
+            if !self.fall(board) { return; }
         }
+        self.fall_progress = progress % ONE_G;
     }
 
-    pub fn draw(&self, canvas: &mut Canvas, board: &Board) {
-        let ghost_i_offset = self.get_ghost_i_offset(board);
-        let mut ghost_color = self.color();
-        ghost_color.a = 0.1;
-        for pos in self.rots[self.rot].iter() {
-            util::draw_cell_indices(canvas, self.i + pos.0 + ghost_i_offset, self.j + pos.1, ghost_color);
-            util::draw_cell_indices(canvas, self.i + pos.0, self.j + pos.1, self.color());
+    //False when the teto is resting on something, without lock delay that locks it
+    fn fall(&mut self, board: &Board) -> bool {
+        self.reset_fall();
+        self.i += 1;
+        if self.collides(board) {
+            self.i -= 1;
+            if self.lock_delay == 0 { self.dead = true; }
+            return false;
         }
+
+        self.last_move_rotated = false;
+        self.lock_frames = 0;
+        true
     }
 
-    pub fn color(&self) -> Color {
-        match self.t {
-            TetoType::I => Color::CYAN,
-            TetoType::O => Color::YELLOW,
-            TetoType::T => Color::MAGENTA,
-            TetoType::S => Color::GREEN,
-            TetoType::Z => Color::RED,
-            TetoType::J => Color::BLUE,
-            TetoType::L => Color::from_rgb(255, 140, 0) //orange
-        }
+    fn is_grounded(&self, board: &Board) -> bool {
+        self.collides_ghost(board, 1)
+    }
+
+    //3-corner rule: a T whose last successful move was a rotation and that has at least 3 of the 4 corners around its center filled
+    //The center is the cell touching the other three, rotation systems don't all keep it in the same place of the shape
+    pub fn is_tspin(&self, board: &Board) -> bool {
+        if !matches!(self.t, TetoType::T) || !self.last_move_rotated { return false; }
+        let cells = self.get_rot();
+        let Some(center) = cells.iter().find(|cell| cells.iter().filter(|other| (other.0 - cell.0).abs() + (other.1 - cell.1).abs() == 1).count() == 3) else { return false; };
+        [(-1, -1), (-1, 1), (1, -1), (1, 1)].iter().filter(|corner| board.filled(center.0 + corner.0, center.1 + corner.1)).count() >= 3
+    }
+
+    pub fn get_type(&self) -> TetoType {
+        self.t
     }
 
     fn get_ghost_i_offset(&self, board: &Board) -> i32 {
@@ -234,8 +376,8 @@ impl Teto {
         false
     }
 
-    fn reset_fall(&mut self, ctx: &Context) {
-        self.prev_fall_time = util::get_time(ctx);
+    fn reset_fall(&mut self) {
+        self.fall_progress = 0;
     }
 
     fn collides(&self, board: &Board) -> bool {
@@ -248,9 +390,23 @@ impl Teto {
     fn collides_cell(&self, board: &Board, i_offset: i32, j_offset: i32) -> bool {
         board.filled(self.i + i_offset, self.j + j_offset)
     }
+}
 
-    fn die(&mut self) {
-        self.dead = true;
-        self.i -= 1;
+#[cfg(feature = "gui")]
+impl Teto {
+    pub fn draw_centered_at(&self, canvas: &mut Canvas, theme: &Theme, x: f32, y: f32) {
+        let starting_i_offset = self.rots[self.rot].iter().map(|pos| pos.0).min().unwrap() as f32;
+        let starting_j_offset = self.rots[self.rot].iter().map(|pos| pos.1).min().unwrap() as f32;
+        let width = self.rots[self.rot].iter().map(|pos| pos.1).max().unwrap() as f32 - starting_j_offset + 1.0;
+        for pos in self.rots[self.rot].iter() {
+            theme.draw_block(canvas, x + (pos.1 as f32 - starting_j_offset - width / 2.0) * CELL_SIZE, y + (pos.0 as f32 - starting_i_offset / 2.0) * CELL_SIZE, theme.color(&self.t));
+        }
     }
-}
\ No newline at end of file
+
+    pub fn draw(&self, canvas: &mut Canvas, board: &Board, theme: &Theme) {
+        for (pos, ghost_pos) in self.get_rot().iter().zip(self.get_ghost_rot(board)) {
+            theme.draw_block_indices(canvas, ghost_pos.0, ghost_pos.1, theme.ghost_color(&self.t));
+            theme.draw_block_indices(canvas, pos.0, pos.1, theme.color(&self.t));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::HashMap, io::Read};
+
+use ggez::{glam::Vec2, graphics::{Canvas, Color, DrawParam, Image}, Context};
+
+use crate::{teto::TetoType, util::{self, CELL_SIZE, UI_SIZE}};
+
+//Themes live in res/themes as "key = value" lines, lines starting with "--" are comments
+//Colors are written as #RRGGBB, anything missing or unreadable falls back to the default look
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub empty: Color,
+    pub grid: Option<Color>,
+    pub text: Color,
+    pub ghost_opacity: f32,
+    colors: HashMap<TetoType, Color>,
+    skin: Option<Image>
+}
+
+impl Theme {
+    pub fn new() -> Self {
+        Self {
+            name: "default".to_owned(),
+            background: Color::new(0.25, 0.25, 0.25, 1.0),
+            empty: Color::BLACK,
+            grid: None,
+            text: Color::WHITE,
+            ghost_opacity: 0.1,
+            colors: HashMap::from([
+                (TetoType::I, Color::CYAN),
+                (TetoType::O, Color::YELLOW),
+                (TetoType::T, Color::MAGENTA),
+                (TetoType::S, Color::GREEN),
+                (TetoType::Z, Color::RED),
+                (TetoType::J, Color::BLUE),
+                (TetoType::L, Color::from_rgb(255, 140, 0)), //orange
+                (TetoType::Garbage, Color::from_rgb(128, 128, 128))
+            ]),
+            skin: None
+        }
+    }
+
+    pub fn list(ctx: &Context) -> Vec<String> {
+        let mut names: Vec<String> = ctx.fs.read_dir("/themes").map(|paths| paths.filter(|path| path.extension().is_some_and(|ext| ext == "theme")).filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned())).collect()).unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    pub fn load(ctx: &Context, name: &str) -> Self {
+        let mut theme = Self::new();
+        theme.name = name.to_owned();
+
+        let mut contents = String::new();
+        if ctx.fs.open(format!("/themes/{}.theme", name)).and_then(|mut file| file.read_to_string(&mut contents).map_err(|e| e.into())).is_err() { return theme; }
+
+        for line in contents.lines() {
+            if line.starts_with("--") { continue; }
+            let Some((key, value)) = line.split_once('=') else { continue; };
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "background" => theme.background = Self::parse_color(value).unwrap_or(theme.background),
+                "empty" => theme.empty = Self::parse_color(value).unwrap_or(theme.empty),
+                "grid" => theme.grid = Self::parse_color(value),
+                "text" => theme.text = Self::parse_color(value).unwrap_or(theme.text),
+                "ghost_opacity" => theme.ghost_opacity = value.parse().unwrap_or(theme.ghost_opacity),
+                "skin" => theme.skin = Image::from_path(ctx, value).ok(),
+                _ => if let Some(teto_type) = TetoType::from_name(key) && let Some(color) = Self::parse_color(value) { theme.colors.insert(teto_type, color); }
+            }
+        }
+
+        theme
+    }
+
+    fn parse_color(value: &str) -> Option<Color> {
+        let rgb = u32::from_str_radix(value.strip_prefix('#')?, 16).ok()?;
+        Some(Color::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+    }
+
+    pub fn color(&self, teto_type: &TetoType) -> Color {
+        self.colors[teto_type]
+    }
+
+    pub fn ghost_color(&self, teto_type: &TetoType) -> Color {
+        let mut color = self.color(teto_type);
+        color.a = self.ghost_opacity;
+        color
+    }
+
+    //Draws a mino with the skin tinted in color, or a flat cell if the theme has no skin
+    pub fn draw_block(&self, canvas: &mut Canvas, x: f32, y: f32, color: Color) {
+        match &self.skin {
+            None => util::draw_cell(canvas, x, y, color),
+            Some(skin) => canvas.draw(skin, DrawParam::new().dest(Vec2::new(x, y)).scale(Vec2::new((CELL_SIZE - 1.0) / skin.width() as f32, (CELL_SIZE - 1.0) / skin.height() as f32)).color(color))
+        }
+    }
+
+    pub fn draw_block_indices(&self, canvas: &mut Canvas, i: i32, j: i32, color: Color) {
+        self.draw_block(canvas, j as f32 * CELL_SIZE + UI_SIZE.x, i as f32 * CELL_SIZE, color);
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+
+use ggez::{glam::Vec2, graphics::{Canvas, Color, Rect}, input::mouse::MouseButton, Context};
+
+use crate::{input::{Action, Input}, theme::Theme, util::{self, CELL_SIZE, SCREEN_SIZE, UI_SIZE}};
+
+const BUTTON_SIZE: f32 = CELL_SIZE * 2.0;
+const BUTTON_SPACING: f32 = CELL_SIZE * 2.4;
+
+//Buttons in the side panels that act like the piece keys while held down with the mouse or a finger
+pub struct TouchControls {
+    buttons: Vec<(Rect, &'static str, Action)>
+}
+
+impl TouchControls {
+    pub fn new() -> Self {
+        let panels = [
+            (UI_SIZE.x / 2.0, [("<", Action::MoveLeft), ("V", Action::SoftDrop), (">", Action::MoveRight), ("DROP", Action::HardDrop)]),
+            (SCREEN_SIZE.x - UI_SIZE.x / 2.0, [("CCW", Action::RotateCcw), ("180", Action::Rotate180), ("CW", Action::RotateCw), ("HOLD", Action::Hold)])
+        ];
+
+        let mut buttons = Vec::new();
+        for (center, panel) in panels {
+            for (n, (text, action)) in panel.into_iter().enumerate() {
+                let x = center + BUTTON_SPACING * (n as f32 - 1.5) - BUTTON_SIZE / 2.0;
+                buttons.push((Rect::new(x, SCREEN_SIZE.y - CELL_SIZE * 0.5 - BUTTON_SIZE, BUTTON_SIZE, BUTTON_SIZE), text, action));
+            }
+        }
+
+        Self {
+            buttons
+        }
+    }
+
+    //mouse_pos is in SCREEN_SIZE coordinates, touches come in as the left mouse button
+    pub fn get_actions(&self, ctx: &Context, mouse_pos: Vec2) -> HashSet<Action> {
+        if !ctx.mouse.button_pressed(MouseButton::Left) { return HashSet::new(); }
+        self.buttons.iter().filter(|button| button.0.contains(mouse_pos)).map(|button| button.2).collect()
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, theme: &Theme, input: &Input) {
+        for (rect, text, action) in self.buttons.iter() {
+            let opacity = if input.is_pressed(*action) { 0.8 } else { 0.4 };
+            util::draw_rect(canvas, rect.x, rect.y, rect.w, rect.h, Color::new(theme.text.r, theme.text.g, theme.text.b, opacity));
+            util::draw_text_centered_on(canvas, text, rect.center().into(), CELL_SIZE * 0.5, theme.background);
+        }
+    }
+}
+
+impl Default for TouchControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
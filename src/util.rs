@@ -1,21 +1,40 @@
-use ggez::{glam::Vec2, graphics::{self, Canvas, Color, Drawable, Rect, Text, TextLayout}, Context};
+use ggez::{glam::Vec2, graphics::{self, Canvas, Color, Drawable, Rect, Text, TextLayout}, input::gamepad::gilrs::{Axis, Button}, Context};
 
-pub struct Index {
-    pub i: i32,
-    pub j: i32
-}
+use crate::board::GRID_SIZE;
 
 pub struct IVec2 {
     pub x: i32,
     pub y: i32
 }
 
-pub const GRID_SIZE: Index = Index { i: 20, j: 10 };
 pub const CELL_SIZE: f32 = 30.0;
 pub const GRID_PIXEL_SIZE: IVec2 = IVec2 { x: GRID_SIZE.j * CELL_SIZE as i32, y: GRID_SIZE.i * CELL_SIZE as i32 };
 pub const UI_SIZE: Vec2 = Vec2 { x: GRID_PIXEL_SIZE.x as f32, y: GRID_PIXEL_SIZE.y as f32 };
 pub const SCREEN_SIZE: Vec2 = Vec2 { x: UI_SIZE.x * 2.0 + GRID_PIXEL_SIZE.x as f32, y: GRID_PIXEL_SIZE.y as f32 };
 
+//Everything is drawn in SCREEN_SIZE coordinates, this is the area of those coordinates that fills a window of window_size
+//It scales the play area to fit while keeping its aspect ratio, leaving bars on the sides that don't fit
+pub fn get_screen_coordinates(window_size: Vec2) -> Rect {
+    let scale = (window_size.x / SCREEN_SIZE.x).min(window_size.y / SCREEN_SIZE.y);
+    let size = window_size / scale;
+    Rect::new((SCREEN_SIZE.x - size.x) / 2.0, (SCREEN_SIZE.y - size.y) / 2.0, size.x, size.y)
+}
+
+//A point of a window of window_size in SCREEN_SIZE coordinates
+pub fn get_screen_pos(screen_coordinates: Rect, window_size: Vec2, pos: Vec2) -> Vec2 {
+    Vec2::new(screen_coordinates.x + pos.x / window_size.x * screen_coordinates.w, screen_coordinates.y + pos.y / window_size.y * screen_coordinates.h)
+}
+
+//The board cell under a point of a window of window_size, if there is one
+pub fn get_cell_at(screen_coordinates: Rect, window_size: Vec2, pos: Vec2) -> Option<(i32, i32)> {
+    let pos = get_screen_pos(screen_coordinates, window_size, pos);
+    let (x, y) = (pos.x - UI_SIZE.x, pos.y);
+    if x < 0.0 || y < 0.0 { return None; }
+
+    let cell = ((y / CELL_SIZE) as i32, (x / CELL_SIZE) as i32);
+    if cell.0 < GRID_SIZE.i && cell.1 < GRID_SIZE.j { Some(cell) } else { None }
+}
+
 pub fn draw(canvas: &mut Canvas, drawable: &impl Drawable, pos: Vec2, color: Color) {
     canvas.draw(drawable, graphics::DrawParam::new().dest(pos).color(color));
 }
@@ -36,10 +55,33 @@ pub fn draw_text_centered_on(canvas: &mut Canvas, text: &str, pos: Vec2, scale:
     draw(canvas, Text::new(text).set_font("font").set_scale(scale).set_layout(TextLayout::center()), pos, color);
 }
 
+//Something that went wrong, over everything else, the message wraps to fit the screen
+pub fn draw_error(canvas: &mut Canvas, message: &str, prompt: &str) {
+    draw_rect(canvas, 0.0, 0.0, SCREEN_SIZE.x, SCREEN_SIZE.y, Color::new(0.0, 0.0, 0.0, 0.95));
+    draw_text_centered_on(canvas, "ERROR", SCREEN_SIZE / 2.0 - Vec2::Y * CELL_SIZE * 4.0, CELL_SIZE, Color::RED);
+    draw(canvas, Text::new(message).set_font("font").set_scale(CELL_SIZE * 0.5).set_bounds(Vec2::new(SCREEN_SIZE.x - CELL_SIZE * 4.0, f32::INFINITY)).set_wrap(true).set_layout(TextLayout::center()), SCREEN_SIZE / 2.0, Color::WHITE);
+    draw_text_centered_on(canvas, prompt, SCREEN_SIZE / 2.0 + Vec2::Y * CELL_SIZE * 4.0, CELL_SIZE * 0.5, Color::WHITE);
+}
+
 pub fn draw_text_centered_on_screen(canvas: &mut Canvas, text: &str, scale: f32, color: Color) {
     draw_text_centered_on(canvas, text, SCREEN_SIZE / 2.0, scale, color);
 }
 
-pub fn get_time(ctx: &Context) -> f32 {
-    ctx.time.time_since_start().as_secs_f32()
+//How far a stick has to be pushed before it counts as the d-pad
+const STICK_DEADZONE: f32 = 0.5;
+
+//Pressed buttons of every connected gamepad, named for Bindings, the left stick works as the d-pad
+pub fn get_pressed_buttons(ctx: &Context) -> Vec<String> {
+    let mut pressed_buttons = Vec::new();
+    for (_, gamepad) in ctx.gamepad.gamepads() {
+        let x = gamepad.value(Axis::LeftStickX);
+        let y = gamepad.value(Axis::LeftStickY);
+        for (button, stick) in [(Button::DPadLeft, x < -STICK_DEADZONE), (Button::DPadRight, x > STICK_DEADZONE), (Button::DPadDown, y < -STICK_DEADZONE), (Button::DPadUp, y > STICK_DEADZONE)] {
+            if stick && !gamepad.is_pressed(button) { pressed_buttons.push(format!("Pad{:?}", button)); }
+        }
+        for button in [Button::South, Button::East, Button::North, Button::West, Button::LeftTrigger, Button::LeftTrigger2, Button::RightTrigger, Button::RightTrigger2, Button::Select, Button::Start, Button::DPadUp, Button::DPadDown, Button::DPadLeft, Button::DPadRight] {
+            if gamepad.is_pressed(button) { pressed_buttons.push(format!("Pad{:?}", button)); }
+        }
+    }
+    pressed_buttons
 }
\ No newline at end of file